@@ -0,0 +1,124 @@
+use maps::Place;
+use maps::export::CSV_COLUMNS;
+use serde_json::Value;
+
+/// The last stored state of a place, read back from the `places` table.
+#[derive(Debug, Clone)]
+pub struct PreviousState {
+    pub place: Place,
+    pub not_found: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// Lists the fields that differ between the stored state and a fresh lookup.
+///
+/// A place that is no longer found only records the `not_found` flip, since
+/// its last known details are kept.
+pub fn diff(previous: &PreviousState, current: Option<&Place>) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    if previous.not_found != current.is_none() {
+        changes.push(FieldChange {
+            field: "not_found".to_string(),
+            old_value: Some(previous.not_found.to_string()),
+            new_value: Some(current.is_none().to_string()),
+        });
+    }
+
+    let Some(current) = current else {
+        return changes;
+    };
+
+    let (Value::Object(old), Value::Object(new)) = (
+        serde_json::to_value(&previous.place).unwrap_or_default(),
+        serde_json::to_value(current).unwrap_or_default(),
+    ) else {
+        return changes;
+    };
+
+    // Place field order, so changes are listed deterministically
+    for field in CSV_COLUMNS {
+        let old_value = old.get(field).cloned().unwrap_or_default();
+        let new_value = new.get(field).cloned().unwrap_or_default();
+        if old_value != new_value {
+            changes.push(FieldChange {
+                field: field.to_string(),
+                old_value: to_text(old_value),
+                new_value: to_text(new_value),
+            });
+        }
+    }
+
+    changes
+}
+
+fn to_text(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place() -> Place {
+        Place {
+            location_id: "0d134e199a405a163".to_string(),
+            title: Some("Empire State Building".to_string()),
+            local_language_title: None,
+            rating: Some(4),
+            phone: Some("(212) 736-3100".to_string()),
+            url: None,
+            menu_url: None,
+            global_code: None,
+            compound_code: None,
+            altitude: None,
+            longitude: None,
+            latitude: None,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let previous = PreviousState { place: place(), not_found: false };
+
+        assert!(diff(&previous, Some(&place())).is_empty());
+
+        let current = Place {
+            phone: None,
+            rating: Some(5),
+            ..place()
+        };
+        assert_eq!(diff(&previous, Some(&current)), vec![
+            FieldChange {
+                field: "rating".to_string(),
+                old_value: Some("4".to_string()),
+                new_value: Some("5".to_string()),
+            },
+            FieldChange {
+                field: "phone".to_string(),
+                old_value: Some("(212) 736-3100".to_string()),
+                new_value: None,
+            },
+        ]);
+
+        assert_eq!(diff(&previous, None), vec![FieldChange {
+            field: "not_found".to_string(),
+            old_value: Some("false".to_string()),
+            new_value: Some("true".to_string()),
+        }]);
+
+        let tombstoned = PreviousState { place: place(), not_found: true };
+        assert!(diff(&tombstoned, None).is_empty());
+        assert_eq!(diff(&tombstoned, Some(&place())).len(), 1);
+    }
+}
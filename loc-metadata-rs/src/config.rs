@@ -0,0 +1,70 @@
+use serde::Deserialize;
+use std::fs::File;
+use std::io::Read;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub options: OptionsConfig,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DatabaseConfig {
+    pub uri: String,
+    pub keyspace: String,
+    pub workers: usize,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct OptionsConfig {
+    pub mode: String,
+    pub max_age_hours: u64,
+    pub input: String,
+    pub input_file: String,
+    pub output: String,
+    pub output_format: String,
+    pub output_file: String,
+    pub max_retries: usize,
+    pub fetchers: usize,
+    pub pool_size: usize,
+}
+
+impl OptionsConfig {
+    /// `max_age_hours` in milliseconds, or `None` if that doesn't fit a timestamp.
+    pub fn max_age_ms(&self) -> Option<i64> {
+        self.max_age_hours.checked_mul(60 * 60 * 1000)
+            .and_then(|ms| i64::try_from(ms).ok())
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let config: Config = serde_yaml::from_str(&contents)?;
+        if config.options.max_age_ms().is_none() {
+            return Err(format!("max_age_hours {} is too large", config.options.max_age_hours).into());
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_age_ms() {
+        let mut options: OptionsConfig = serde_yaml::from_str(include_str!("../config.yaml"))
+            .map(|config: Config| config.options)
+            .unwrap();
+        options.max_age_hours = 168;
+        assert_eq!(options.max_age_ms(), Some(604_800_000));
+        options.max_age_hours = u64::MAX / 1000;
+        assert_eq!(options.max_age_ms(), None);
+        options.max_age_hours = i64::MAX as u64 / 3_600_000 + 1;
+        assert_eq!(options.max_age_ms(), None);
+    }
+}
@@ -0,0 +1,208 @@
+use scylla::transport::errors::QueryError;
+use scylla::batch::Batch;
+use std::sync::Arc;
+use async_channel::Receiver;
+use tracing::{error, info};
+use thiserror::Error;
+use scylla::{Session, SessionBuilder};
+use std::process;
+use tokio::time::{sleep, Duration};
+use scylla::frame::value::{CqlTimestamp, MaybeUnset};
+use scylla::serialize::row::SerializeRow;
+use crate::workers::FetchedPlace;
+
+const BATCH_SIZE: usize = 100;
+
+const MIGRATIONS: [&str; 2] = [
+    include_str!("../migrations/001_create_places.cql"),
+    include_str!("../migrations/002_create_place_changes.cql"),
+];
+
+// Unset columns are left untouched, so a not found row keeps the last known details.
+type PlaceRow = (
+    String,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<i32>>,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<String>>,
+    MaybeUnset<Option<i64>>,
+    MaybeUnset<Option<i64>>,
+    MaybeUnset<Option<i64>>,
+    CqlTimestamp,
+    bool,
+);
+
+type ChangeRow = (String, CqlTimestamp, String, Option<String>, Option<String>);
+
+#[derive(Error, Debug)]
+pub enum OutputError {
+    #[error("Scylla query error: {0}")]
+    Scylla(#[from] QueryError),
+    #[error("Channel receive error: {0}")]
+    Channel(#[from] async_channel::RecvError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Export error: {0}")]
+    Export(#[from] maps::export::ExportError),
+    #[error("Unknown output format: {0}")]
+    UnknownFormat(String),
+}
+
+pub async fn create_db_session(uri: &str, keyspace: &str) -> Arc<Session> {
+    match SessionBuilder::new()
+        .known_node(uri)
+        .use_keyspace(keyspace, true)
+        .build()
+        .await
+    {
+        Ok(session) => Arc::new(session),
+        Err(e) => {
+            eprintln!("Failed to connect to Scylla DB at {}: {}", uri, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Creates the tables used by this program if they don't exist yet.
+pub async fn run_migrations(session: &Session) -> Result<(), OutputError> {
+    for migration in MIGRATIONS {
+        for statement in migration.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            session.query(statement, &[]).await?;
+        }
+    }
+    info!("Database migrations applied");
+    Ok(())
+}
+
+pub async fn handle_db_insertions(session: Arc<Session>, rx_db: Receiver<FetchedPlace>) -> Result<(), OutputError> {
+    info!("Database connection established");
+    let mut stmt = session.prepare(
+        "INSERT INTO places (location_id, title, local_language_title, rating, phone, url, menu_url, \
+         global_code, compound_code, altitude, longitude, latitude, fetched_at, not_found) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    ).await?;
+    stmt.set_is_idempotent(true);
+    let mut change_stmt = session.prepare(
+        "INSERT INTO place_changes (location_id, changed_at, field, old_value, new_value) VALUES (?, ?, ?, ?, ?)"
+    ).await?;
+    change_stmt.set_is_idempotent(true);
+
+    let mut batch = new_batch();
+    let mut batch_values: Vec<PlaceRow> = Vec::with_capacity(BATCH_SIZE);
+    let mut change_batch = new_batch();
+    let mut change_values: Vec<ChangeRow> = Vec::with_capacity(BATCH_SIZE);
+
+    while let Ok(mut fetched) = rx_db.recv().await {
+        for change in fetched.changes.drain(..) {
+            change_batch.append_statement(change_stmt.clone());
+            change_values.push((
+                fetched.location_id.clone(),
+                CqlTimestamp(fetched.fetched_at),
+                change.field,
+                change.old_value,
+                change.new_value,
+            ));
+        }
+
+        batch.append_statement(stmt.clone());
+        batch_values.push(to_row(fetched));
+
+        // Changes go first so a crash never leaves a refreshed row without its history
+        if batch_values.len() >= BATCH_SIZE || change_values.len() >= BATCH_SIZE {
+            execute_batch(&session, &change_batch, &change_values).await;
+            change_values.clear();
+            change_batch = new_batch();
+
+            execute_batch(&session, &batch, &batch_values).await;
+            batch_values.clear();
+            batch = new_batch();
+        }
+    }
+
+    // Execute any remaining batches
+    if !change_values.is_empty() {
+        execute_batch(&session, &change_batch, &change_values).await;
+    }
+    if !batch_values.is_empty() {
+        execute_batch(&session, &batch, &batch_values).await;
+    }
+
+    info!("Database insertion handler completed");
+    Ok(())
+}
+
+fn new_batch() -> Batch {
+    let mut batch = Batch::default();
+    batch.set_is_idempotent(true);
+    batch
+}
+
+fn to_row(fetched: FetchedPlace) -> PlaceRow {
+    let fetched_at = CqlTimestamp(fetched.fetched_at);
+    match fetched.place {
+        Some(place) => (
+            fetched.location_id,
+            MaybeUnset::Set(place.title),
+            MaybeUnset::Set(place.local_language_title),
+            MaybeUnset::Set(place.rating),
+            MaybeUnset::Set(place.phone),
+            MaybeUnset::Set(place.url),
+            MaybeUnset::Set(place.menu_url),
+            MaybeUnset::Set(place.global_code),
+            MaybeUnset::Set(place.compound_code),
+            MaybeUnset::Set(place.altitude),
+            MaybeUnset::Set(place.longitude),
+            MaybeUnset::Set(place.latitude),
+            fetched_at,
+            false,
+        ),
+        None => (
+            fetched.location_id,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            MaybeUnset::Unset,
+            fetched_at,
+            true,
+        ),
+    }
+}
+
+
+async fn execute_batch<T: SerializeRow>(
+    session: &Session,
+    batch: &Batch,
+    values: &[T],
+) {
+    let mut retry_count: i32 = 0;
+    let max_retries: i32 = 5;
+    // Doubles after every failure, so an outage isn't hammered with retries
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        match session.batch(batch, values).await {
+            Ok(_) => break,
+            Err(e) => {
+                if retry_count >= max_retries {
+                    error!("Failed to execute batch after {} retries: {:?}", max_retries, e);
+                    return;
+                }
+                error!(attempt = retry_count + 1, "Error executing batch: {:?}. Retrying in {:?}...", e, delay);
+                sleep(delay).await;
+                delay *= 2;
+                retry_count += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use async_channel::Sender;
+use futures::StreamExt;
+use scylla::Session;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::info;
+use maps::Place;
+use scylla::frame::value::CqlTimestamp;
+use crate::changes::PreviousState;
+use crate::workers::FetchJob;
+
+type StoredPlaceRow = (
+    String,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+    Option<CqlTimestamp>,
+    Option<bool>,
+);
+
+/// Sends every location ID from a `locations-rs` output file, one ID per line.
+/// Files written with `coordinates` are read too: the `id,lat,lng,...` header
+/// is skipped and only the first column is used.
+pub async fn read_ids_from_file(
+    tx_fetcher: Sender<FetchJob>,
+    filename: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let file = File::open(filename).await?;
+    let mut lines = BufReader::new(file).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if let Some(location_id) = location_id_from_line(&line) {
+            tx_fetcher.send(FetchJob { location_id: location_id.to_string(), previous: None }).await?;
+        }
+    }
+
+    info!("Finished reading location IDs from {}", filename);
+    Ok(())
+}
+
+fn location_id_from_line(line: &str) -> Option<&str> {
+    let location_id = line.split(',').next()?.trim();
+    match location_id {
+        "" | "id" => None,
+        location_id => Some(location_id),
+    }
+}
+
+/// Sends every location ID from the `locations` table.
+pub async fn read_ids_from_db(
+    tx_fetcher: Sender<FetchJob>,
+    session: Arc<Session>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut rows = session.query_iter("SELECT location_id FROM locations", &[]).await?
+        .into_typed::<(String,)>();
+
+    while let Some(row) = rows.next().await {
+        let (location_id,) = row?;
+        tx_fetcher.send(FetchJob { location_id, previous: None }).await?;
+    }
+
+    info!("Finished reading location IDs from the locations table");
+    Ok(())
+}
+
+/// Sends every place from the `places` table that was last fetched more than
+/// `max_age_ms` ago, along with its stored state.
+pub async fn read_stale_from_db(
+    tx_fetcher: Sender<FetchJob>,
+    session: Arc<Session>,
+    max_age_ms: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cutoff = crate::workers::now_millis().saturating_sub(max_age_ms);
+    // Filtered by the database and read page by page, so only stale rows
+    // leave it. Every row written by this program has a `fetched_at`.
+    let mut rows = session.query_iter(
+        "SELECT location_id, title, local_language_title, rating, phone, url, menu_url, \
+         global_code, compound_code, altitude, longitude, latitude, fetched_at, not_found FROM places \
+         WHERE fetched_at < ? ALLOW FILTERING",
+        (CqlTimestamp(cutoff),),
+    ).await?
+        .into_typed::<StoredPlaceRow>();
+
+    let mut stale = 0;
+    while let Some(row) = rows.next().await {
+        let row = row?;
+
+        let previous = PreviousState {
+            place: Place {
+                location_id: row.0.clone(),
+                title: row.1,
+                local_language_title: row.2,
+                rating: row.3,
+                phone: row.4,
+                url: row.5,
+                menu_url: row.6,
+                global_code: row.7,
+                compound_code: row.8,
+                altitude: row.9,
+                longitude: row.10,
+                latitude: row.11,
+            },
+            not_found: row.13.unwrap_or(false),
+        };
+        tx_fetcher.send(FetchJob { location_id: row.0, previous: Some(previous) }).await?;
+        stale += 1;
+    }
+
+    info!("Finished reading {} stale places from the places table", stale);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_ids_from_file() {
+        let path = std::env::temp_dir().join(format!("loc-metadata-input-{}.csv", std::process::id()));
+        tokio::fs::write(&path, "id,lat,lng,zoom,x,y\n\
+            0d134e199a405a163,40.7484405,-73.9856644,16,19299,24631\n\
+            1a2b3c4d5e6f70809,,,,,\n\
+            \n\
+            2f00000000000000a\n").await.unwrap();
+
+        let (tx, rx) = async_channel::unbounded();
+        read_ids_from_file(tx, path.to_str().unwrap()).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut ids = Vec::new();
+        while let Ok(job) = rx.try_recv() {
+            ids.push(job.location_id);
+        }
+        assert_eq!(ids, ["0d134e199a405a163", "1a2b3c4d5e6f70809", "2f00000000000000a"]);
+    }
+}
@@ -0,0 +1,41 @@
+use maps::{GetPlaceError, MapsJsInternalClient, Place, RequestError};
+use tokio::time::{sleep, Duration};
+use tracing::{warn, error};
+use std::sync::Arc;
+use crate::status::CounterType;
+
+/// Fetches a place, retrying on errors. Returns `Ok(None)` if the place doesn't exist.
+pub async fn get_place_with_retries(
+    client: &MapsJsInternalClient,
+    location_id: &str,
+    status: &Arc<super::status::ProgramStatus>,
+    max_retries: usize,
+) -> Result<Option<Place>, super::workers::WorkerError> {
+    let delay = Duration::from_secs(1);
+
+    for i in 1..max_retries+1 {
+        status.increment(CounterType::Request);
+        match client.get_place(location_id.to_string()).send().await {
+            Ok(place) => {
+                return Ok(Some(place))
+            },
+            Err(GetPlaceError::RequestError(RequestError::NotFound)) => {
+                status.increment(CounterType::NotFound);
+                return Ok(None)
+            },
+            Err(GetPlaceError::RequestError(RequestError::RateLimited)) => {
+                status.increment(CounterType::Ratelimit);
+                warn!("Rate limited when fetching place {}.", location_id);
+                sleep(delay).await;
+            },
+            Err(e) => {
+                status.increment(CounterType::Error);
+                error!("Error in get_place_with_retries (attempt {}): {}", i, e);
+                sleep(delay).await;
+            }
+        }
+    }
+
+    status.increment(CounterType::Failed);
+    Err(super::workers::WorkerError::MaxRetriesExceeded)
+}
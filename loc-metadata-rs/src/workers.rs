@@ -0,0 +1,106 @@
+use async_channel::{Receiver, Sender};
+use maps::export::{CsvWriter, JsonLinesWriter, PlaceWriter};
+use maps::{MapsJsInternalClient, Place};
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::error;
+use crate::changes::{self, FieldChange, PreviousState};
+use crate::status::CounterType;
+
+/// A place ID to look up, with its stored state when refreshing.
+pub struct FetchJob {
+    pub location_id: String,
+    pub previous: Option<PreviousState>,
+}
+
+/// Outcome of a lookup. `place` is `None` if the place no longer exists.
+pub struct FetchedPlace {
+    pub location_id: String,
+    pub place: Option<Place>,
+    /// Milliseconds since the unix epoch.
+    pub fetched_at: i64,
+    /// Fields that differ from the stored state, empty unless refreshing.
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Error, Debug)]
+pub enum WorkerError {
+    #[error("Output channel closed")]
+    ChannelClosed,
+    #[error("Max retries exceeded")]
+    MaxRetriesExceeded,
+}
+
+
+pub async fn fetcher(
+    client: MapsJsInternalClient,
+    max_retries: usize,
+    rx_fetcher: Receiver<FetchJob>,
+    tx_out: Sender<FetchedPlace>,
+    status: Arc<super::status::ProgramStatus>,
+) -> Result<(), WorkerError> {
+
+    while let Ok(FetchJob { location_id, previous }) = rx_fetcher.recv().await {
+        status.update_last_id(location_id.clone());
+        let place = match super::retry::get_place_with_retries(&client, &location_id, &status, max_retries).await {
+            Ok(place) => place,
+            // One bad ID shouldn't stop the fetcher, it is counted as failed
+            Err(e) => {
+                error!("Giving up on place {}: {}", location_id, e);
+                continue;
+            }
+        };
+
+        if place.is_some() {
+            status.increment(CounterType::Found);
+        }
+        let changes = previous
+            .map(|previous| changes::diff(&previous, place.as_ref()))
+            .unwrap_or_default();
+        if !changes.is_empty() {
+            status.increment(CounterType::Changed);
+        }
+        let fetched = FetchedPlace {
+            location_id,
+            place,
+            fetched_at: now_millis(),
+            changes,
+        };
+        tx_out.send(fetched).await.map_err(|_| WorkerError::ChannelClosed)?;
+    }
+
+    Ok(())
+}
+
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Writes found places to `filename` in the given format (`jsonl` or `csv`).
+pub async fn file_writer(rx_out: Receiver<FetchedPlace>, filename: String, format: String) -> Result<(), crate::db::OutputError> {
+    tokio::task::spawn_blocking(move || {
+        let file = BufWriter::new(File::create(&filename)?);
+        let mut writer: Box<dyn PlaceWriter> = match format.as_str() {
+            "jsonl" => Box::new(JsonLinesWriter::new(file)),
+            "csv" => Box::new(CsvWriter::new(file)),
+            _ => return Err(crate::db::OutputError::UnknownFormat(format)),
+        };
+
+        while let Ok(fetched) = rx_out.recv_blocking() {
+            if let Some(place) = fetched.place {
+                writer.write_place(&place)?;
+            }
+        }
+
+        writer.finish()?;
+        Ok(())
+    })
+    .await
+    .expect("file writer panicked")
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use std::time::Duration;
+use maps::tiles::{FetchTilesError, Tile, TilesClient};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use crate::config::CanaryConfig;
+
+/// Holds fetchers back while the canary is failing.
+#[derive(Clone)]
+pub struct CanaryGate {
+    open: Arc<watch::Sender<bool>>,
+}
+
+impl CanaryGate {
+    pub fn new(open: bool) -> Self {
+        Self { open: Arc::new(watch::Sender::new(open)) }
+    }
+
+    /// Returns once the gate is open.
+    pub async fn wait(&self) {
+        let _ = self.open.subscribe().wait_for(|open| *open).await;
+    }
+
+    pub fn is_open(&self) -> bool {
+        *self.open.borrow()
+    }
+
+    fn set(&self, open: bool) {
+        self.open.send_replace(open);
+    }
+}
+
+/// A known tile and feature IDs that must be in it.
+#[derive(Debug, Clone)]
+pub struct CanaryTile {
+    pub tile: Tile,
+    pub feature_ids: Vec<String>,
+}
+
+pub fn canary_tiles(config: &CanaryConfig) -> Vec<CanaryTile> {
+    if config.tiles.is_empty() {
+        panic!("the canary is enabled but has no `tiles`");
+    }
+    config.tiles.iter()
+        .map(|canary| CanaryTile {
+            tile: canary.tile.parse().unwrap_or_else(|e| panic!("invalid canary tile: {}", e)),
+            feature_ids: canary.feature_ids.iter().map(|id| id.to_lowercase()).collect(),
+        })
+        .collect()
+}
+
+/// Fetches every canary tile, returning why the first bad one is bad.
+pub async fn check(client: &TilesClient, tiles: &[CanaryTile]) -> Result<(), String> {
+    for canary in tiles {
+        let batch = vec![canary.tile.clone()];
+        let result = match client.view_tiles_with_report(&batch).await {
+            // The client has switched to the newer version already
+            Err(FetchTilesError::StaleMapVersion(_)) => client.view_tiles_with_report(&batch).await,
+            result => result,
+        };
+        let (ids, report) = result.map_err(|e| format!("tile {}: {}", canary.tile, e))?;
+
+        // Skipped features alone happen on healthy tiles, and a strict client
+        // has already turned them into an error above
+        if report.non_array_roots > 0 || report.unknown_shapes > 0 {
+            return Err(format!("tile {}: {}", canary.tile, report));
+        }
+        if report.skipped_features > 0 {
+            warn!("Canary tile {}: {}", canary.tile, report);
+        }
+        if ids.is_empty() {
+            return Err(format!("tile {}: no features", canary.tile));
+        }
+        let missing: Vec<&str> = canary.feature_ids.iter()
+            .filter(|id| !ids.contains(id))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("tile {}: missing features {}", canary.tile, missing.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the canary tiles forever, opening `gate` while they pass. The gate
+/// is closed after `failures_before_pause` failures in a row.
+pub async fn run_canary(client: TilesClient, tiles: Vec<CanaryTile>, config: &CanaryConfig, gate: CanaryGate) {
+    let mut failures = 0;
+    loop {
+        match check(&client, &tiles).await {
+            Ok(()) => {
+                if !gate.is_open() {
+                    info!("Canary passed, fetchers are running");
+                }
+                failures = 0;
+                gate.set(true);
+            }
+            Err(reason) => {
+                failures += 1;
+                if !gate.is_open() {
+                    error!("Canary failed, fetchers are paused until it passes: {}", reason);
+                } else if failures >= config.failures_before_pause {
+                    gate.set(false);
+                    error!("CANARY FAILED {} TIMES IN A ROW, PAUSING ALL FETCHERS until it passes. The tile endpoint may have changed: {}", failures, reason);
+                } else {
+                    warn!("Canary failed ({}/{}): {}", failures, config.failures_before_pause, reason);
+                }
+            }
+        }
+
+        let wait = match gate.is_open() && failures == 0 {
+            true => Duration::from_secs(config.interval_minutes * 60),
+            false => Duration::from_secs(config.recheck_secs),
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maps_testkit::{synthetic_features, Fault, MapsTestServer};
+
+    #[tokio::test]
+    async fn test_check() {
+        let server = MapsTestServer::start().await.unwrap();
+        let client = TilesClient::new(crate::utils::initialize_client().unwrap()).base_url(server.tiles_url());
+
+        let tile = (0..16).map(|x| Tile { x, y: 7, zoom: 16 })
+            .find(|tile| !synthetic_features(tile.zoom, tile.x, tile.y).is_empty())
+            .unwrap();
+        let known = synthetic_features(tile.zoom, tile.x, tile.y)[0].hex_id();
+        let canary = |feature_ids: Vec<String>| vec![CanaryTile { tile: tile.clone(), feature_ids }];
+
+        assert_eq!(check(&client, &canary(vec![known.clone()])).await, Ok(()));
+        assert!(check(&client, &canary(vec![known.clone(), "1".to_string()])).await.unwrap_err().contains("missing features 1"));
+
+        server.tile_faults().push(Fault::ServerError(503));
+        assert!(check(&client, &canary(vec![known.clone()])).await.is_err());
+
+        // A stale map version is refreshed rather than failing the canary
+        server.set_map_version("702460000");
+        assert_eq!(check(&client, &canary(vec![known])).await, Ok(()));
+        assert_eq!(client.current_map_version(), "702460000");
+    }
+
+    #[tokio::test]
+    async fn test_run_canary() {
+        let server = MapsTestServer::start().await.unwrap();
+        let client = TilesClient::new(crate::utils::initialize_client().unwrap()).base_url(server.tiles_url());
+        let tile = (0..16).map(|x| Tile { x, y: 7, zoom: 16 })
+            .find(|tile| !synthetic_features(tile.zoom, tile.x, tile.y).is_empty())
+            .unwrap();
+        let tiles = vec![CanaryTile { tile, feature_ids: Vec::new() }];
+        let config = CanaryConfig {
+            enabled: true,
+            interval_minutes: 10,
+            recheck_secs: 0,
+            failures_before_pause: 2,
+            tiles: Vec::new(),
+        };
+
+        server.tile_faults().every(1, Fault::ServerError(503));
+        let gate = CanaryGate::new(true);
+        let mut open = gate.open.subscribe();
+        let canary = tokio::spawn({
+            let gate = gate.clone();
+            async move { run_canary(client, tiles, &config, gate).await }
+        });
+
+        let within = Duration::from_secs(10);
+        tokio::time::timeout(within, open.wait_for(|open| !*open)).await.unwrap().unwrap();
+        assert!(server.tile_requests() >= 2);
+
+        // Reopened by the next recheck once the tile endpoint is healthy again
+        server.tile_faults().clear();
+        tokio::time::timeout(within, gate.wait()).await.unwrap();
+        assert!(gate.is_open());
+        canary.abort();
+    }
+}
@@ -7,6 +7,14 @@ pub struct Config {
     pub database: DatabaseConfig,
     pub options: OptionsConfig,
     pub tile_generation: TileGenerationConfig,
+    #[serde(default)]
+    pub tile_cache: TileCacheConfig,
+    #[serde(default)]
+    pub tile_archive: TileArchiveConfig,
+    #[serde(default)]
+    pub map_version: MapVersionConfig,
+    #[serde(default)]
+    pub canary: CanaryConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -22,6 +30,10 @@ pub struct OptionsConfig {
     pub output: String,
     pub max_retries: usize,
     pub fetchers: usize,
+    #[serde(default)]
+    pub strict_parsing: bool,
+    #[serde(default)]
+    pub coordinates: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +46,95 @@ pub struct TileGenerationConfig {
     pub zoom: usize,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TileCacheConfig {
+    pub enabled: bool,
+    pub path: String,
+    pub ttl_hours: u64,
+    pub max_size_mb: u64,
+    pub cache_only: bool,
+}
+
+impl Default for TileCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "tile_cache.redb".to_string(),
+            ttl_hours: 720,
+            max_size_mb: 4096,
+            cache_only: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TileArchiveConfig {
+    pub enabled: bool,
+    pub dir: String,
+    pub segment_size_mb: u64,
+}
+
+impl Default for TileArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "tile_archive".to_string(),
+            segment_size_mb: 256,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MapVersionConfig {
+    pub version: Option<String>,
+    pub bootstrap_url: Option<String>,
+    pub cache_path: String,
+    pub max_age_hours: u64,
+}
+
+impl Default for MapVersionConfig {
+    fn default() -> Self {
+        Self {
+            version: None,
+            bootstrap_url: None,
+            cache_path: "map_version.json".to_string(),
+            max_age_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CanaryConfig {
+    pub enabled: bool,
+    pub interval_minutes: u64,
+    pub recheck_secs: u64,
+    pub failures_before_pause: usize,
+    pub tiles: Vec<CanaryTileConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CanaryTileConfig {
+    pub tile: String,
+    #[serde(default)]
+    pub feature_ids: Vec<String>,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 10,
+            recheck_secs: 60,
+            failures_before_pause: 3,
+            tiles: Vec::new(),
+        }
+    }
+}
+
 impl Config {
     pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let mut file = File::open(path)?;
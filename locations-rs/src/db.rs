@@ -12,11 +12,11 @@ const BATCH_SIZE: usize = 200;
 #[derive(Error, Debug)]
 pub enum OutputError {
     #[error("Scylla query error: {0}")]
-    ScyllaError(#[from] QueryError),
+    Scylla(#[from] QueryError),
     #[error("Channel receive error: {0}")]
-    ChannelError(#[from] async_channel::RecvError),
+    Channel(#[from] async_channel::RecvError),
     #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
+    Io(#[from] std::io::Error),
 }
 
 pub async fn create_db_session(uri: &str, keyspace: &str) -> Arc<Session> {
@@ -1,5 +1,6 @@
 
 use std::sync::Arc;
+use std::time::Duration;
 
 mod config;
 mod workers;
@@ -8,12 +9,22 @@ mod retry;
 mod tiles;
 mod utils;
 mod db;
+mod reprocess;
+mod canary;
 
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let config = config::Config::load("config.yaml").unwrap();
 
+    // `reprocess [dir]` re-parses a tile archive instead of crawling
+    let args: Vec<String> = std::env::args().collect();
+    let reprocess_dir = match args.get(1).map(String::as_str) {
+        Some("reprocess") => Some(args.get(2).cloned().unwrap_or_else(|| config.tile_archive.dir.clone())),
+        Some(command) => panic!("unknown command `{}`! the only command is `reprocess [archive dir]`", command),
+        None => None,
+    };
+
     let file_appender = tracing_appender::rolling::hourly("logs", "maps_locations.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
     tracing_subscriber::fmt()
@@ -25,7 +36,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let (tx_out, rx_out) = async_channel::bounded(1000);
 
     let program_status = Arc::new(status::ProgramStatus::new());
-    let client = utils::initialize_client()?;
+    let mut client = maps::tiles::TilesClient::new(utils::initialize_client()?)
+        .strict(config.options.strict_parsing);
+    // Reprocessing doesn't request tiles, so it doesn't need the current version
+    if reprocess_dir.is_none() {
+        client = utils::init_map_version(client, &config.map_version).await;
+    }
+    // Cloned before the cache is added, so the canary always hits the endpoint
+    let canary_client = client.clone();
+    if config.tile_cache.enabled {
+        let cache = maps::tiles::TileCache::open(&config.tile_cache.path, Duration::from_secs(config.tile_cache.ttl_hours * 3600))?
+            .max_bytes(config.tile_cache.max_size_mb * 1024 * 1024)
+            .cache_only(config.tile_cache.cache_only);
+        client = client.cache(cache);
+    }
+    let mut archive = None;
+    if config.tile_archive.enabled && reprocess_dir.is_none() {
+        let tile_archive = maps::tiles::TileArchive::with_segment_size(&config.tile_archive.dir, config.tile_archive.segment_size_mb * 1024 * 1024).await?;
+        client = client.archive(tile_archive.clone());
+        archive = Some(tile_archive);
+    }
 
     let channel_info = Arc::new(status::ChannelInfo {
         tx_fetcher: tx_fetcher.clone(),
@@ -33,12 +63,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     });
 
     let status_clone = program_status.clone();
+    let status_client = client.clone();
     tokio::spawn(async move {
-        status::run_status_logger(status_clone, channel_info).await;
+        status::run_status_logger(status_clone, channel_info, status_client).await;
     });
 
     let mut output_handles = vec![];
 
+    if config.options.coordinates && config.options.output != "file" {
+        panic!("`coordinates` is only supported with the `file` output");
+    }
+
     if config.options.output == "database" {
         let session = db::create_db_session(&config.database.uri, &config.database.keyspace).await;
         for _ in 0..config.database.workers {
@@ -50,12 +85,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             output_handles.push(output_handle);
         }
     } else if config.options.output == "file" {
-        let output_handle = tokio::spawn(workers::file_writer(rx_out.clone(), "output.csv"));
+        let header = config.options.coordinates.then_some(workers::COORDINATES_HEADER);
+        let output_handle = tokio::spawn(workers::file_writer(rx_out.clone(), "output.csv", header));
         output_handles.push(output_handle)
     } else {
         panic!("no valid output specified! choose either `file` or `database`");
     }
-    
+
+    if let Some(dir) = reprocess_dir {
+        reprocess::reprocess_archive(dir, config.options.coordinates, tx_out.clone(), Arc::clone(&program_status)).await?;
+        tx_out.close();
+        for output_handle in output_handles {
+            output_handle.await??;
+        }
+        return Ok(());
+    }
+
+    let canary_enabled = config.canary.enabled && !config.tile_cache.cache_only;
+    let canary_gate = canary::CanaryGate::new(!canary_enabled);
+    if canary_enabled {
+        let tiles = canary::canary_tiles(&config.canary);
+        let gate = canary_gate.clone();
+        tokio::spawn(async move {
+            canary::run_canary(canary_client, tiles, &config.canary, gate).await;
+        });
+    }
 
     // spawn fetchers
     let mut fetcher_handles = vec![];
@@ -64,21 +118,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let fetcher_tx_out = tx_out.clone();
         let fetcher_status = Arc::clone(&program_status);
         let fetcher_client = client.clone();
+        let fetcher_canary = canary_gate.clone();
         let handle = tokio::spawn(async move {
-            workers::fetcher(fetcher_client, config.options.max_retries, fetcher_rx, fetcher_tx_out, fetcher_status).await
+            workers::fetcher(fetcher_client, config.options.max_retries, config.options.coordinates, fetcher_canary, fetcher_rx, fetcher_tx_out, fetcher_status).await
         });
         fetcher_handles.push(handle);
     }
 
     // Start tile generation and sending
-    tiles::generate_and_send_tiles(tx_fetcher.clone(), &config.tile_generation).await?;
+    let crawled: Result<(), Box<dyn std::error::Error + Send + Sync>> = async {
+        tiles::generate_and_send_tiles(tx_fetcher.clone(), &config.tile_generation).await?;
+
+        tx_fetcher.close();
 
-    tx_fetcher.close();
+        // Wait for fetchers to complete
+        for handle in fetcher_handles {
+            handle.await??;
+        }
+        Ok(())
+    }.await;
 
-    // Wait for fetchers to complete
-    for handle in fetcher_handles {
-        handle.await??;
+    // Flushed even if the crawl failed, so the last archived responses aren't lost
+    if let Some(archive) = archive {
+        archive.flush().await?;
     }
+    crawled?;
 
     tx_out.close();
     
@@ -0,0 +1,55 @@
+use maps::tiles::TileArchiveReader;
+use async_channel::Sender;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use crate::status::CounterType;
+
+/// Re-parses every response in the tile archive at `dir` with the current
+/// parser and sends the location IDs to the outputs, without any requests.
+pub async fn reprocess_archive(
+    dir: String,
+    coordinates: bool,
+    tx_out: Sender<Vec<String>>,
+    status: Arc<super::status::ProgramStatus>,
+) -> Result<(), super::workers::WorkerError> {
+    info!("Reprocessing tile archive {}", dir);
+
+    tokio::task::spawn_blocking(move || {
+        for record in TileArchiveReader::open(&dir)? {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    error!("Skipping damaged archive record: {}", e);
+                    status.increment(CounterType::Error);
+                    continue;
+                }
+            };
+            if let Some(tile) = record.tiles.first() {
+                status.update_last_tile(tile.clone());
+            }
+
+            let result = match coordinates {
+                true => maps::tiles::parse_positions_with_report(&record.body, &record.tiles)
+                    .map(|(features, report)| (features.iter().map(super::workers::coordinate_row).collect(), report)),
+                false => maps::tiles::parse_ids_with_report(&record.body),
+            };
+            match result {
+                Ok((ids, report)) => {
+                    if !report.is_clean() {
+                        warn!("Skipped parts of an archived response: {}", report);
+                        status.increment_count(CounterType::ParseIssue, report.total());
+                    }
+                    if !ids.is_empty() {
+                        status.increment_count(CounterType::Found, ids.len());
+                        tx_out.send_blocking(ids)?;
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to parse archived response: {}", e);
+                    status.increment(CounterType::Error);
+                }
+            }
+        }
+        Ok(())
+    }).await.map_err(std::io::Error::other)?
+}
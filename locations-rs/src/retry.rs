@@ -1,30 +1,54 @@
 
-use hyper_util::client::legacy::Client;
-use hyper_util::client::legacy::connect::HttpConnector;
-use hyper_tls::HttpsConnector;
+use maps::tiles::TilesClient;
 use tokio::time::{sleep, Duration};
 use tracing::{warn, error};
-use http_body_util::Empty;
 use std::sync::Arc;
-use bytes::Bytes;
 use crate::status::CounterType;
 
 pub async fn view_tiles_with_retries(
-    client: &mut Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+    client: &TilesClient,
     tiles: Vec<maps::tiles::Tile>,
     status: &Arc<super::status::ProgramStatus>,
     max_retries: usize,
+    coordinates: bool,
+    canary: &super::canary::CanaryGate,
 ) -> Result<Vec<String>, super::workers::WorkerError> {
     let delay = Duration::from_secs(1);
 
     for i in 1..max_retries+1 {
+        // Attempts aren't used up while the canary is failing
+        canary.wait().await;
         status.increment(CounterType::Request);
-        match maps::tiles::view_tiles(&client, &tiles).await {
-            Ok(response) => {
+        let result = match coordinates {
+            true => client.view_tile_positions(&tiles).await
+                .map(|(features, report)| (features.iter().map(super::workers::coordinate_row).collect(), report)),
+            false => client.view_tiles_with_report(&tiles).await,
+        };
+        match result {
+            Ok((response, report)) => {
+                if !report.is_clean() {
+                    warn!("Skipped parts of the response for tiles starting at {}: {}", tiles[0], report);
+                    status.increment_count(CounterType::ParseIssue, report.total());
+                }
+                // Offline runs skip tiles missing from the cache instead of retrying
+                if report.not_cached > 0 {
+                    warn!("{} tiles not in cache in the batch starting at {}", report.not_cached, tiles[0]);
+                    status.increment_count(CounterType::NotCached, report.not_cached);
+                }
                 return Ok(response)
             },
             Err(e) => {
                 match e {
+                    // The format changed, so retrying won't help
+                    maps::tiles::FetchTilesError::UnexpectedShape(report) => {
+                        error!("Unexpected response shape for tiles starting at {}: {}", tiles[0], report);
+                        status.increment_count(CounterType::ParseIssue, report.total());
+                        return Err(super::workers::WorkerError::UnexpectedShape(report));
+                    }
+                    // Already switched to the newer version, so retry right away
+                    maps::tiles::FetchTilesError::StaleMapVersion(stale) => {
+                        warn!("Map version {} was retired, switched to {}", stale, client.current_map_version());
+                    }
                     maps::tiles::FetchTilesError::UnexpectedStatusCode(status_code) => {
                         warn!("Unexpected status code {} when fetching tiles.", status_code);
                         sleep(delay).await;
@@ -40,5 +64,5 @@ pub async fn view_tiles_with_retries(
     }
 
     status.increment(CounterType::Failed);
-    return Err(super::workers::WorkerError::MaxRetriesExceeded);
+    Err(super::workers::WorkerError::MaxRetriesExceeded)
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use parking_lot::RwLock;
 use std::sync::Arc;
 use tracing::{info, Instrument};
-use maps::tiles::Tile;
+use maps::tiles::{Tile, TilesClient};
 use async_channel::Sender;
 use tokio::time::Duration;
 
@@ -20,6 +20,8 @@ struct StatusCounters {
     error_count: AtomicUsize,
     found_count: AtomicUsize,
     failed_count: AtomicUsize,
+    not_cached_count: AtomicUsize,
+    parse_issue_count: AtomicUsize,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -29,6 +31,8 @@ pub enum CounterType {
     Error,
     Found,
     Failed,
+    NotCached,
+    ParseIssue,
 }
 
 impl ProgramStatus {
@@ -52,6 +56,8 @@ impl ProgramStatus {
             CounterType::Error => counters.error_count.fetch_add(1, Ordering::Relaxed),
             CounterType::Found => counters.found_count.fetch_add(1, Ordering::Relaxed),
             CounterType::Failed => counters.failed_count.fetch_add(1, Ordering::Relaxed),
+            CounterType::NotCached => counters.not_cached_count.fetch_add(1, Ordering::Relaxed),
+            CounterType::ParseIssue => counters.parse_issue_count.fetch_add(1, Ordering::Relaxed),
         };
     }
 
@@ -63,6 +69,8 @@ impl ProgramStatus {
             CounterType::Error => counters.error_count.fetch_add(count, Ordering::Relaxed),
             CounterType::Found => counters.found_count.fetch_add(count, Ordering::Relaxed),
             CounterType::Failed => counters.failed_count.fetch_add(count, Ordering::Relaxed),
+            CounterType::NotCached => counters.not_cached_count.fetch_add(count, Ordering::Relaxed),
+            CounterType::ParseIssue => counters.parse_issue_count.fetch_add(count, Ordering::Relaxed),
         };
     }
 
@@ -85,6 +93,8 @@ impl ProgramStatus {
         match counter {
             CounterType::Found => counters.found_count.load(Ordering::Relaxed),
             CounterType::Failed => counters.failed_count.load(Ordering::Relaxed),
+            CounterType::NotCached => counters.not_cached_count.load(Ordering::Relaxed),
+            CounterType::ParseIssue => counters.parse_issue_count.load(Ordering::Relaxed),
             _ => panic!("Unsupported counter type for get"),
         }
     }
@@ -100,6 +110,8 @@ impl ProgramStatus {
             errors: self.get_and_reset(CounterType::Error),
             found: self.get(CounterType::Found),
             failed: self.get(CounterType::Failed),
+            not_cached: self.get(CounterType::NotCached),
+            parse_issues: self.get(CounterType::ParseIssue),
             last_tile: self.get_last_input(),
         }
     }
@@ -111,6 +123,8 @@ struct StatusMetrics {
     errors: usize,
     found: usize,
     failed: usize,
+    not_cached: usize,
+    parse_issues: usize,
     last_tile: Arc<Tile>,
 }
 
@@ -119,12 +133,13 @@ pub struct ChannelInfo {
     pub tx_out: Sender<Vec<String>>,
 }
 
-pub async fn log_status(status: Arc<ProgramStatus>, channel_info: Arc<ChannelInfo>) {
+pub async fn log_status(status: Arc<ProgramStatus>, channel_info: Arc<ChannelInfo>, client: TilesClient) {
     let mut interval = tokio::time::interval(Duration::from_secs(LOG_INTERVAL_SECS));
     loop {
         interval.tick().await;
         let metrics = status.get_metrics();
         let rps = metrics.requests as f64 / LOG_INTERVAL_SECS as f64;
+        let bytes = client.byte_counts();
 
         info!(
             rps = rps,
@@ -137,13 +152,18 @@ pub async fn log_status(status: Arc<ProgramStatus>, channel_info: Arc<ChannelInf
             error_last_interval = metrics.errors,
             found = metrics.found,
             failed = metrics.failed,
+            not_cached = metrics.not_cached,
+            parse_issues = metrics.parse_issues,
+            bytes_received = bytes.received,
+            bytes_decoded = bytes.decoded,
+            map_version = %client.current_map_version(),
             "stats"
         );
     }
 }
 
-pub async fn run_status_logger(status: Arc<ProgramStatus>, channel_info: Arc<ChannelInfo>) {
-    log_status(status, channel_info)
+pub async fn run_status_logger(status: Arc<ProgramStatus>, channel_info: Arc<ChannelInfo>, client: TilesClient) {
+    log_status(status, channel_info, client)
         .instrument(tracing::info_span!("status_logger"))
         .await;
 }
\ No newline at end of file
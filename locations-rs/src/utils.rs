@@ -6,6 +6,9 @@ use native_tls::TlsConnector;
 use http_body_util::Empty;
 use bytes::Bytes;
 use thiserror::Error;
+use tracing::{info, warn};
+use maps::tiles::{MapVersionCache, TilesClient};
+use crate::config::MapVersionConfig;
 
 #[derive(Error, Debug)]
 pub enum ClientError {
@@ -31,4 +34,42 @@ pub fn initialize_client() -> Result<Client<HttpsConnector<HttpConnector>, Empty
         .build::<_, Empty<Bytes>>(https);
 
     Ok(client)
-}
\ No newline at end of file
+}
+/// Sets the map version the crawl starts with: the configured one, else a
+/// recently discovered one from the cache, else a freshly discovered one. If
+/// discovery fails the built-in version is kept.
+pub async fn init_map_version(client: TilesClient, config: &MapVersionConfig) -> TilesClient {
+    let cache = MapVersionCache::new(&config.cache_path, std::time::Duration::from_secs(config.max_age_hours * 3600));
+    let mut client = client.map_version_cache(cache.clone());
+    if let Some(url) = &config.bootstrap_url {
+        client = client.bootstrap_url(url);
+    }
+
+    if let Some(version) = &config.version {
+        info!("Using configured map version {}", version);
+        return client.map_version(version);
+    }
+
+    match cache.load().await {
+        Ok(Some(version)) => {
+            info!("Using cached map version {}", version);
+            return client.map_version(version);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read map version cache {}: {}", config.cache_path, e),
+    }
+
+    match client.discover_map_version().await {
+        Ok(version) => {
+            info!("Discovered map version {}", version);
+            if let Err(e) = cache.store(&version).await {
+                warn!("Failed to write map version cache {}: {}", config.cache_path, e);
+            }
+            client.map_version(version)
+        }
+        Err(e) => {
+            warn!("Failed to discover the map version, using {}: {}", client.current_map_version(), e);
+            client
+        }
+    }
+}
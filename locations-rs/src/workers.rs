@@ -1,8 +1,4 @@
-use hyper_util::client::legacy::Client;
-use hyper_util::client::legacy::connect::HttpConnector;
-use hyper_tls::HttpsConnector;
-use bytes::Bytes;
-use http_body_util::Empty;
+use maps::tiles::{TileFeature, TilesClient};
 use async_channel::{Receiver, Sender};
 use std::sync::Arc;
 use thiserror::Error;
@@ -20,14 +16,18 @@ pub enum WorkerError {
     ReceiveError(#[from] async_channel::RecvError),
     #[error("Max retries exceeded")]
     MaxRetriesExceeded,
+    #[error("Unexpected tile response shape: {0}")]
+    UnexpectedShape(maps::tiles::ParseReport),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
 
 
 pub async fn fetcher(
-    mut client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+    client: TilesClient,
     max_retries: usize,
+    coordinates: bool,
+    canary: super::canary::CanaryGate,
     rx_fetcher: Receiver<Vec<maps::tiles::Tile>>,
     tx_out: Sender<Vec<String>>,
     status: Arc<super::status::ProgramStatus>,
@@ -35,8 +35,8 @@ pub async fn fetcher(
 
     while let Ok(tiles) = rx_fetcher.recv().await {
         status.update_last_tile(tiles[0].clone());
-        let location_ids = super::retry::view_tiles_with_retries(&mut client, tiles, &status, max_retries).await?;
-        if location_ids.len() != 0 {
+        let location_ids = super::retry::view_tiles_with_retries(&client, tiles, &status, max_retries, coordinates, &canary).await?;
+        if !location_ids.is_empty() {
             status.increment_count(CounterType::Found, location_ids.len());
             tx_out.send(location_ids).await?;
         }
@@ -45,10 +45,29 @@ pub async fn fetcher(
     Ok(())
 }
 
-pub async fn file_writer(rx_out: Receiver<Vec<String>>, filename: &str) -> Result<(), crate::db::OutputError> {
+/// Header of the rows written by [`coordinate_row`].
+pub const COORDINATES_HEADER: &str = "id,lat,lng,zoom,x,y";
+
+/// Formats a feature as a CSV row, leaving out what isn't known.
+pub fn coordinate_row(feature: &TileFeature) -> String {
+    let (lat, lng) = feature.lat_lng
+        .map(|(lat, lng)| (format!("{:.7}", lat), format!("{:.7}", lng)))
+        .unwrap_or_default();
+    let (zoom, x, y) = feature.tile.as_ref()
+        .map(|tile| (tile.zoom.to_string(), tile.x.to_string(), tile.y.to_string()))
+        .unwrap_or_default();
+    format!("{},{},{},{},{},{}", feature.id, lat, lng, zoom, x, y)
+}
+
+pub async fn file_writer(rx_out: Receiver<Vec<String>>, filename: &str, header: Option<&str>) -> Result<(), crate::db::OutputError> {
     let file = File::create(filename).await?;
     let mut writer = BufWriter::new(file);
 
+    if let Some(header) = header {
+        writer.write_all(header.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+    }
+
     while let Ok(locations) = rx_out.recv().await {
         for location in locations {
             writer.write_all(location.as_bytes()).await?;
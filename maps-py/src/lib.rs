@@ -0,0 +1,309 @@
+//! Python bindings for the `maps` crate, importable as `google_maps`.
+//!
+//! Requests run on a shared tokio runtime. The `*_async` variants return
+//! awaitables for asyncio; the others block without holding the GIL.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+use bytes::Bytes;
+use http_body_util::Empty;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use maps::{GetPlaceError, RequestError};
+
+create_exception!(google_maps, MapsError, PyException);
+create_exception!(google_maps, NotFoundError, MapsError);
+create_exception!(google_maps, RateLimitedError, MapsError);
+
+type TileClient = Client<HttpsConnector<HttpConnector>, Empty<Bytes>>;
+
+static TILE_CLIENT: OnceLock<TileClient> = OnceLock::new();
+
+fn runtime() -> &'static tokio::runtime::Runtime {
+    pyo3_async_runtimes::tokio::get_runtime()
+}
+
+fn tile_client() -> TileClient {
+    TILE_CLIENT.get_or_init(|| {
+        let _guard = runtime().enter();
+        Client::builder(TokioExecutor::new())
+            .build::<_, Empty<Bytes>>(HttpsConnector::new())
+    }).clone()
+}
+
+fn to_py_err(e: GetPlaceError) -> PyErr {
+    match e {
+        GetPlaceError::RequestError(RequestError::NotFound) => NotFoundError::new_err(e.to_string()),
+        GetPlaceError::RequestError(RequestError::RateLimited) => RateLimitedError::new_err(e.to_string()),
+        e => MapsError::new_err(e.to_string()),
+    }
+}
+
+#[pyclass(name = "Tile", module = "google_maps", eq, frozen)]
+#[derive(Debug, Clone, PartialEq)]
+struct PyTile {
+    #[pyo3(get)]
+    x: usize,
+    #[pyo3(get)]
+    y: usize,
+    #[pyo3(get)]
+    zoom: usize,
+}
+
+impl From<&PyTile> for maps::tiles::Tile {
+    fn from(tile: &PyTile) -> Self {
+        maps::tiles::Tile { x: tile.x, y: tile.y, zoom: tile.zoom }
+    }
+}
+
+#[pymethods]
+impl PyTile {
+    #[new]
+    fn new(x: usize, y: usize, zoom: usize) -> Self {
+        Self { x, y, zoom }
+    }
+
+    /// Parses `zoom/x/y`.
+    #[staticmethod]
+    fn parse(s: &str) -> PyResult<Self> {
+        let tile: maps::tiles::Tile = s.parse()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(Self { x: tile.x, y: tile.y, zoom: tile.zoom })
+    }
+
+    /// Raises `ValueError` if `zoom` is 32 or more.
+    #[staticmethod]
+    fn from_lat_lng(latitude: f64, longitude: f64, zoom: usize) -> PyResult<Self> {
+        let tile = maps::tiles::Tile::from_lat_lng(latitude, longitude, zoom)
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(Self { x: tile.x, y: tile.y, zoom: tile.zoom })
+    }
+
+    /// Returns `(latitude, longitude)` of the tile center.
+    fn center(&self) -> (f64, f64) {
+        maps::tiles::Tile::from(self).center()
+    }
+
+    fn __str__(&self) -> String {
+        maps::tiles::Tile::from(self).to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Tile(x={}, y={}, zoom={})", self.x, self.y, self.zoom)
+    }
+
+    fn __hash__(&self) -> u64 {
+        ((self.zoom as u64) << 58) ^ ((self.x as u64) << 29) ^ self.y as u64
+    }
+}
+
+#[pyclass(name = "Place", module = "google_maps", frozen)]
+struct PyPlace(maps::Place);
+
+#[pymethods]
+impl PyPlace {
+    #[getter]
+    fn location_id(&self) -> &str {
+        &self.0.location_id
+    }
+
+    #[getter]
+    fn title(&self) -> Option<&str> {
+        self.0.title.as_deref()
+    }
+
+    #[getter]
+    fn local_language_title(&self) -> Option<&str> {
+        self.0.local_language_title.as_deref()
+    }
+
+    #[getter]
+    fn rating(&self) -> Option<i32> {
+        self.0.rating
+    }
+
+    #[getter]
+    fn phone(&self) -> Option<&str> {
+        self.0.phone.as_deref()
+    }
+
+    #[getter]
+    fn url(&self) -> Option<&str> {
+        self.0.url.as_deref()
+    }
+
+    #[getter]
+    fn menu_url(&self) -> Option<&str> {
+        self.0.menu_url.as_deref()
+    }
+
+    #[getter]
+    fn global_code(&self) -> Option<&str> {
+        self.0.global_code.as_deref()
+    }
+
+    #[getter]
+    fn compound_code(&self) -> Option<&str> {
+        self.0.compound_code.as_deref()
+    }
+
+    #[getter]
+    fn altitude(&self) -> Option<i64> {
+        self.0.altitude
+    }
+
+    /// Longitude in E7 units, as returned by the API.
+    #[getter]
+    fn longitude(&self) -> Option<i64> {
+        self.0.longitude
+    }
+
+    /// Latitude in E7 units, as returned by the API.
+    #[getter]
+    fn latitude(&self) -> Option<i64> {
+        self.0.latitude
+    }
+
+    /// Returns `(latitude, longitude)` in degrees.
+    fn lat_lng(&self) -> Option<(f64, f64)> {
+        self.0.lat_lng()
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0)
+            .map_err(|e| MapsError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Place(location_id={:?}, title={:?})", self.0.location_id, self.0.title)
+    }
+}
+
+#[pyclass(name = "MapsJsInternalClient", module = "google_maps", frozen)]
+struct PyMapsJsInternalClient(maps::MapsJsInternalClient);
+
+#[pymethods]
+impl PyMapsJsInternalClient {
+    /// `timeout` is in seconds.
+    #[new]
+    #[pyo3(signature = (pool_size=1, timeout=None))]
+    fn new(py: Python<'_>, pool_size: usize, timeout: Option<f64>) -> PyResult<Self> {
+        let mut builder = maps::MapsJsInternalClient::builder().pool_size(pool_size);
+        if let Some(timeout) = timeout {
+            let timeout = Duration::try_from_secs_f64(timeout)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("invalid timeout {}: {}", timeout, e)))?;
+            builder = builder.timeout(Some(timeout));
+        }
+
+        let client = py.allow_threads(|| runtime().block_on(builder.build())
+            .map_err(|e| MapsError::new_err(e.to_string())))?;
+        Ok(Self(client))
+    }
+
+    #[pyo3(signature = (location_id, language=None, region=None))]
+    fn get_place(&self, py: Python<'_>, location_id: String, language: Option<String>, region: Option<String>) -> PyResult<PyPlace> {
+        let request = self.request(location_id, language, region);
+        py.allow_threads(|| runtime().block_on(request.send())
+            .map(PyPlace)
+            .map_err(to_py_err))
+    }
+
+    #[pyo3(signature = (location_id, language=None, region=None))]
+    fn get_place_async<'py>(&self, py: Python<'py>, location_id: String, language: Option<String>, region: Option<String>) -> PyResult<Bound<'py, PyAny>> {
+        let request = self.request(location_id, language, region);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            request.send().await
+                .map(PyPlace)
+                .map_err(to_py_err)
+        })
+    }
+}
+
+impl PyMapsJsInternalClient {
+    fn request(&self, location_id: String, language: Option<String>, region: Option<String>) -> maps::GetPlaceRequest {
+        let mut request = self.0.get_place(location_id);
+        if let Some(language) = language {
+            request = request.language(language);
+        }
+        if let Some(region) = region {
+            request = request.region(region);
+        }
+        request
+    }
+}
+
+/// Returns the hex IDs of the features in `tiles`.
+#[pyfunction]
+fn view_tiles(py: Python<'_>, tiles: Vec<PyRef<'_, PyTile>>) -> PyResult<Vec<String>> {
+    let tiles: Vec<maps::tiles::Tile> = tiles.iter().map(|tile| maps::tiles::Tile::from(&**tile)).collect();
+    let client = tile_client();
+    py.allow_threads(|| runtime().block_on(maps::tiles::view_tiles(&client, &tiles)))
+        .map_err(|e| MapsError::new_err(e.to_string()))
+}
+
+#[pyfunction]
+fn view_tiles_async<'py>(py: Python<'py>, tiles: Vec<PyRef<'py, PyTile>>) -> PyResult<Bound<'py, PyAny>> {
+    let tiles: Vec<maps::tiles::Tile> = tiles.iter().map(|tile| maps::tiles::Tile::from(&**tile)).collect();
+    let client = tile_client();
+    pyo3_async_runtimes::tokio::future_into_py(py, async move {
+        maps::tiles::view_tiles(&client, &tiles).await
+            .map_err(|e| MapsError::new_err(e.to_string()))
+    })
+}
+
+#[pymodule]
+fn google_maps(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTile>()?;
+    m.add_class::<PyPlace>()?;
+    m.add_class::<PyMapsJsInternalClient>()?;
+    m.add_function(wrap_pyfunction!(view_tiles, m)?)?;
+    m.add_function(wrap_pyfunction!(view_tiles_async, m)?)?;
+    m.add("MapsError", m.py().get_type::<MapsError>())?;
+    m.add("NotFoundError", m.py().get_type::<NotFoundError>())?;
+    m.add("RateLimitedError", m.py().get_type::<RateLimitedError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::exceptions::PyValueError;
+
+    #[test]
+    fn test_tile() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let tile = PyTile::parse("16/19299/24631").unwrap();
+            assert_eq!((tile.zoom, tile.x, tile.y), (16, 19299, 24631));
+            assert_eq!(tile.__str__(), "16/19299/24631");
+            assert!(PyTile::parse("16/19299").unwrap_err().is_instance_of::<PyValueError>(py));
+            assert_eq!(PyTile::from_lat_lng(40.7484405, -73.9856644, 16).unwrap(), tile);
+            assert!(PyTile::from_lat_lng(0.0, 0.0, 64).unwrap_err().is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn test_client_invalid_timeout() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            for timeout in [-1.0, f64::NAN, f64::INFINITY] {
+                let result = PyMapsJsInternalClient::new(py, 1, Some(timeout));
+                assert!(result.err().unwrap().is_instance_of::<PyValueError>(py));
+            }
+        });
+    }
+
+    #[test]
+    fn test_errors() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert!(to_py_err(GetPlaceError::RequestError(RequestError::NotFound)).is_instance_of::<NotFoundError>(py));
+            assert!(to_py_err(GetPlaceError::RequestError(RequestError::RateLimited)).is_instance_of::<MapsError>(py));
+        });
+    }
+}
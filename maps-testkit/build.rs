@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let proto_files = [
+        "../maps/proto/mapsjs.proto",
+    ];
+
+    let proto_paths = [
+        PathBuf::from("../maps/proto"),
+    ];
+
+    tonic_build::configure()
+        .build_server(true)
+        .build_client(false)
+        .compile_protos(&proto_files, &proto_paths)?;
+
+    Ok(())
+}
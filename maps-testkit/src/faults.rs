@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use parking_lot::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// HTTP 429, or `RESOURCE_EXHAUSTED` over gRPC.
+    RateLimit,
+    /// The given 5xx status, or `INTERNAL`/`UNAVAILABLE` over gRPC.
+    ServerError(u16),
+    /// Waits before answering normally.
+    Latency(Duration),
+    /// A 200 whose JSON body is cut off halfway. Tiles only; `DATA_LOSS` over gRPC.
+    TruncatedJson,
+    /// A 200 with Google's "unusual traffic" HTML page. Tiles only;
+    /// `PERMISSION_DENIED` over gRPC.
+    Captcha,
+    /// A 200 whose JSON body starts with the `)]}'` anti-XSSI prefix, as some
+    /// Google endpoints send. Tiles only; answered normally over gRPC.
+    XssiPrefix,
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<Fault>,
+    every: Option<(usize, usize, Fault)>,
+    latency: Option<Duration>,
+    requests: usize,
+}
+
+/// Fault schedule for one fake server. Clones share the same schedule.
+#[derive(Clone, Default)]
+pub struct Faults {
+    state: Arc<Mutex<State>>,
+}
+
+impl Faults {
+    /// Answers the next request with `fault`. Queued faults are used in order.
+    pub fn push(&self, fault: Fault) {
+        self.state.lock().queue.push_back(fault);
+    }
+
+    /// Answers the next `count` requests with `fault`.
+    pub fn push_n(&self, fault: Fault, count: usize) {
+        self.state.lock().queue.extend(std::iter::repeat_n(fault, count));
+    }
+
+    /// Answers every `n`th request with `fault`, counting from the next one.
+    pub fn every(&self, n: usize, fault: Fault) {
+        let mut state = self.state.lock();
+        state.every = Some((n.max(1), state.requests, fault));
+    }
+
+    /// Delays every response by `latency`.
+    pub fn latency(&self, latency: Duration) {
+        self.state.lock().latency = Some(latency);
+    }
+
+    /// Removes all scheduled faults and latency.
+    pub fn clear(&self) {
+        *self.state.lock() = State::default();
+    }
+
+    /// Returns the latency to apply and the fault for the current request, if any.
+    pub(crate) fn next(&self) -> (Option<Duration>, Option<Fault>) {
+        let mut state = self.state.lock();
+        state.requests += 1;
+
+        let fault = state.queue.pop_front().or_else(|| match &state.every {
+            Some((n, start, fault)) if (state.requests - start).is_multiple_of(*n) => Some(fault.clone()),
+            _ => None,
+        });
+        (state.latency, fault)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let faults = Faults::default();
+        faults.push(Fault::RateLimit);
+        faults.every(3, Fault::ServerError(503));
+
+        let schedule: Vec<Option<Fault>> = (0..7).map(|_| faults.next().1).collect();
+        assert_eq!(schedule, vec![
+            Some(Fault::RateLimit),
+            None,
+            Some(Fault::ServerError(503)),
+            None,
+            None,
+            Some(Fault::ServerError(503)),
+            None,
+        ]);
+    }
+}
@@ -0,0 +1,94 @@
+use std::convert::Infallible;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, Response, StatusCode};
+use prost::Message;
+use tonic::Status;
+use crate::mapsjs::GetEntityDetailsRequest;
+use crate::mapsjs::maps_js_internal_service_server::MapsJsInternalService;
+use crate::places::PlaceService;
+
+const GET_ENTITY_DETAILS_PATH: &str = "/google.internal.maps.mapsjs.v1.MapsJsInternalService/GetEntityDetails";
+const TRAILER_FLAG: u8 = 0x80;
+
+/// The fake place service behind gRPC-web on HTTP/1.1, in binary
+/// (`application/grpc-web+proto`) and text (`application/grpc-web-text`) mode.
+pub(crate) struct GrpcWebService {
+    pub(crate) places: PlaceService,
+}
+
+impl GrpcWebService {
+    pub(crate) async fn handle(&self, request: Request<hyper::body::Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+        if request.method() != Method::POST || request.uri().path() != GET_ENTITY_DETAILS_PATH {
+            return Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Full::new(Bytes::from_static(b"not found")))
+                .unwrap());
+        }
+
+        let content_type = request.headers().get(hyper::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/grpc-web+proto")
+            .to_string();
+        let text = content_type.starts_with("application/grpc-web-text");
+
+        let body = match request.into_body().collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => return Ok(respond(&content_type, text, Err(Status::invalid_argument(e.to_string())))),
+        };
+        let message = match decode_request(body, text) {
+            Ok(message) => message,
+            Err(e) => return Ok(respond(&content_type, text, Err(Status::invalid_argument(e)))),
+        };
+
+        let result = self.places.get_entity_details(tonic::Request::new(message)).await
+            .map(|response| response.into_inner().encode_to_vec());
+        Ok(respond(&content_type, text, result))
+    }
+}
+
+fn decode_request(body: Bytes, text: bool) -> Result<GetEntityDetailsRequest, String> {
+    let mut body = match text {
+        true => Bytes::from(STANDARD.decode(&body).map_err(|e| e.to_string())?),
+        false => body,
+    };
+    if body.remaining() < 5 || body.get_u8() != 0 {
+        return Err("expected a single message frame".to_string());
+    }
+    let len = body.get_u32() as usize;
+    if body.remaining() < len {
+        return Err("truncated message frame".to_string());
+    }
+    GetEntityDetailsRequest::decode(body.split_to(len)).map_err(|e| e.to_string())
+}
+
+/// Answers with a message and trailers, or only trailers for an error.
+fn respond(content_type: &str, text: bool, result: Result<Vec<u8>, Status>) -> Response<Full<Bytes>> {
+    let (message, code, status_message) = match result {
+        Ok(message) => (Some(message), 0, String::new()),
+        Err(status) => (None, status.code() as i32, status.message().to_string()),
+    };
+
+    let mut body = BytesMut::new();
+    if let Some(message) = message {
+        body.put_u8(0);
+        body.put_u32(message.len() as u32);
+        body.put_slice(&message);
+    }
+    let trailers = format!("grpc-status:{}\r\ngrpc-message:{}\r\n", code, status_message);
+    body.put_u8(TRAILER_FLAG);
+    body.put_u32(trailers.len() as u32);
+    body.put_slice(trailers.as_bytes());
+
+    let body = match text {
+        true => Bytes::from(STANDARD.encode(body)),
+        false => body.freeze(),
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(hyper::header::CONTENT_TYPE, content_type)
+        .body(Full::new(body))
+        .unwrap()
+}
@@ -0,0 +1,201 @@
+//! Local fakes of the Google Maps endpoints used by the `maps` crate, so
+//! `view_tiles` and `GetPlaceRequest::send` can be tested without hitting
+//! Google.
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! let server = maps_testkit::MapsTestServer::start().await?;
+//! server.tile_faults().push(maps_testkit::Fault::RateLimit);
+//! // Point `TilesClient::base_url` at `server.tiles_url()` and
+//! // `MapsJsInternalClientBuilder::url` at `server.places_url()`, or at
+//! // `server.grpc_web_url()` with the gRPC-web transport.
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Request, Response};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use parking_lot::Mutex;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio_stream::wrappers::TcpListenerStream;
+
+mod faults;
+mod tiles;
+mod places;
+mod grpc_web;
+
+pub use faults::{Fault, Faults};
+pub use tiles::{synthetic_features, decode_pb, SyntheticFeature, INITIAL_MAP_VERSION};
+pub use places::{synthetic_place, PlaceStore};
+
+pub mod mapsjs {
+    tonic::include_proto!("google.internal.maps.mapsjs.v1");
+}
+
+use mapsjs::maps_js_internal_service_server::MapsJsInternalServiceServer;
+
+/// A fake `/maps/vt` HTTP server and a fake `MapsJsInternalService` served
+/// over gRPC and gRPC-web, all on localhost. They shut down when this is
+/// dropped.
+pub struct MapsTestServer {
+    tiles_addr: SocketAddr,
+    places_addr: SocketAddr,
+    grpc_web_addr: SocketAddr,
+    tile_faults: Faults,
+    place_faults: Faults,
+    map_version: Arc<Mutex<String>>,
+    places: PlaceStore,
+    tile_requests: Arc<AtomicUsize>,
+    place_requests: Arc<AtomicUsize>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl MapsTestServer {
+    pub async fn start() -> io::Result<Self> {
+        let (shutdown, _) = watch::channel(false);
+
+        let tile_faults = Faults::default();
+        let tile_requests = Arc::new(AtomicUsize::new(0));
+        let map_version = Arc::new(Mutex::new(tiles::INITIAL_MAP_VERSION.to_string()));
+        let tiles_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let tiles_addr = tiles_listener.local_addr()?;
+        let service = Arc::new(tiles::TileService {
+            faults: tile_faults.clone(),
+            map_version: map_version.clone(),
+            requests: tile_requests.clone(),
+        });
+        tokio::spawn(serve_http(tiles_listener, move |request| {
+            let service = service.clone();
+            async move { service.handle(request).await }
+        }, shutdown.subscribe()));
+
+        let place_faults = Faults::default();
+        let place_requests = Arc::new(AtomicUsize::new(0));
+        let places = PlaceStore::default();
+        let places_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let places_addr = places_listener.local_addr()?;
+        let service = places::PlaceService {
+            store: places.clone(),
+            faults: place_faults.clone(),
+            requests: place_requests.clone(),
+        };
+        let grpc_web_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let grpc_web_addr = grpc_web_listener.local_addr()?;
+        let grpc_web = Arc::new(grpc_web::GrpcWebService {
+            places: places::PlaceService {
+                store: places.clone(),
+                faults: place_faults.clone(),
+                requests: place_requests.clone(),
+            },
+        });
+        tokio::spawn(serve_http(grpc_web_listener, move |request| {
+            let service = grpc_web.clone();
+            async move { service.handle(request).await }
+        }, shutdown.subscribe()));
+
+        let mut rx = shutdown.subscribe();
+        tokio::spawn(tonic::transport::Server::builder()
+            .add_service(MapsJsInternalServiceServer::new(service))
+            .serve_with_incoming_shutdown(TcpListenerStream::new(places_listener), async move {
+                let _ = rx.wait_for(|stop| *stop).await;
+            }));
+
+        Ok(Self {
+            tiles_addr,
+            places_addr,
+            grpc_web_addr,
+            tile_faults,
+            place_faults,
+            map_version,
+            places,
+            tile_requests,
+            place_requests,
+            shutdown,
+        })
+    }
+
+    /// Base URL for `TilesClient::base_url`.
+    pub fn tiles_url(&self) -> String {
+        format!("http://{}", self.tiles_addr)
+    }
+
+    /// URL for `MapsJsInternalClientBuilder::url`.
+    pub fn places_url(&self) -> String {
+        format!("http://{}", self.places_addr)
+    }
+
+    /// URL for `MapsJsInternalClientBuilder::url` with `Transport::GrpcWeb`.
+    /// Shares places, faults and the request count with [`Self::places_url`].
+    pub fn grpc_web_url(&self) -> String {
+        format!("http://{}", self.grpc_web_addr)
+    }
+
+    pub fn tile_faults(&self) -> &Faults {
+        &self.tile_faults
+    }
+
+    pub fn place_faults(&self) -> &Faults {
+        &self.place_faults
+    }
+
+    /// Retires the current map version. Tile requests for any other version
+    /// get a 404, and `/maps/api/js` announces `map_version`.
+    pub fn set_map_version(&self, map_version: impl Into<String>) {
+        *self.map_version.lock() = map_version.into();
+    }
+
+    pub fn places(&self) -> &PlaceStore {
+        &self.places
+    }
+
+    /// Number of requests the tile server has received, including faulted ones.
+    pub fn tile_requests(&self) -> usize {
+        self.tile_requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of `GetEntityDetails` calls received, including faulted ones.
+    pub fn place_requests(&self) -> usize {
+        self.place_requests.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MapsTestServer {
+    fn drop(&mut self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+async fn serve_http<F, Fut>(listener: TcpListener, handle: F, mut shutdown: watch::Receiver<bool>)
+where
+    F: Fn(Request<Incoming>) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<Response<Full<Bytes>>, Infallible>> + Send + 'static,
+{
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            },
+            _ = shutdown.wait_for(|stop| *stop) => return,
+        };
+
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service_fn(handle))
+                .await;
+        });
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use parking_lot::Mutex;
+use tonic::{Request, Response, Status};
+use crate::faults::{Fault, Faults};
+use crate::mapsjs::maps_js_internal_service_server::MapsJsInternalService;
+use crate::mapsjs::{GetEntityDetailsRequest, GetEntityDetailsResponse};
+use crate::mapsjs::get_entity_details_response::{Camera, EntityDetailsResult, camera::Location, entity_details_result::AuthorityPageLink};
+
+/// Places known to the fake server. Unknown IDs get a synthetic place unless
+/// they were marked as not found.
+#[derive(Clone, Default)]
+pub struct PlaceStore {
+    overrides: Arc<Mutex<HashMap<String, GetEntityDetailsResponse>>>,
+    not_found: Arc<Mutex<HashSet<String>>>,
+}
+
+impl PlaceStore {
+    /// Serves `response` for `feature_id` instead of a synthetic place.
+    pub fn insert(&self, feature_id: impl Into<String>, response: GetEntityDetailsResponse) {
+        self.overrides.lock().insert(feature_id.into(), response);
+    }
+
+    /// Answers lookups of `feature_id` with `NOT_FOUND`.
+    pub fn not_found(&self, feature_id: impl Into<String>) {
+        self.not_found.lock().insert(feature_id.into());
+    }
+
+    fn get(&self, feature_id: &str, language: &str) -> Option<GetEntityDetailsResponse> {
+        if self.not_found.lock().contains(feature_id) {
+            return None;
+        }
+        if let Some(response) = self.overrides.lock().get(feature_id) {
+            return Some(response.clone());
+        }
+        Some(synthetic_place(feature_id, language))
+    }
+}
+
+/// Returns the response the fake server sends for `feature_id` when it
+/// hasn't been overridden. `local_language_title` carries the language.
+pub fn synthetic_place(feature_id: &str, language: &str) -> GetEntityDetailsResponse {
+    // FNV-1a, so the same ID always gets the same details
+    let hash = feature_id.bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+
+    let latitude = (hash % 1_700_000_000) as i64 - 850_000_000;
+    let longitude = ((hash >> 16) % 3_600_000_000) as i64 - 1_800_000_000;
+
+    GetEntityDetailsResponse {
+        entity_details_result: Some(EntityDetailsResult {
+            title: format!("Place {}", feature_id),
+            num_rating_stars: (hash % 5) as i32 + 1,
+            phone_number: format!("+1 555-{:04}", hash % 10_000),
+            authority_page_link: Some(AuthorityPageLink {
+                url: format!("/url?q=https://example.com/{}&opi=1", feature_id),
+            }),
+            category: "Test".to_string(),
+            single_line_address: format!("{} Test Street", hash % 1000),
+            menu_link: None,
+            local_language_title: format!("Place {} ({})", feature_id, language),
+            plus_code: None,
+        }),
+        camera: Some(Camera {
+            location: Some(Location {
+                altitude: "0".to_string(),
+                longitude: longitude.to_string(),
+                latitude: latitude.to_string(),
+            }),
+        }),
+    }
+}
+
+pub(crate) struct PlaceService {
+    pub(crate) store: PlaceStore,
+    pub(crate) faults: Faults,
+    pub(crate) requests: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl MapsJsInternalService for PlaceService {
+    async fn get_entity_details(&self, request: Request<GetEntityDetailsRequest>) -> Result<Response<GetEntityDetailsResponse>, Status> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+
+        let (latency, fault) = self.faults.next();
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        match fault {
+            None | Some(Fault::XssiPrefix) => {}
+            Some(Fault::Latency(latency)) => tokio::time::sleep(latency).await,
+            Some(Fault::RateLimit) => return Err(Status::resource_exhausted("rate limited")),
+            Some(Fault::ServerError(503)) => return Err(Status::unavailable("server error")),
+            Some(Fault::ServerError(_)) => return Err(Status::internal("server error")),
+            Some(Fault::TruncatedJson) => return Err(Status::data_loss("truncated response")),
+            Some(Fault::Captcha) => return Err(Status::permission_denied("unusual traffic")),
+        }
+
+        let request = request.into_inner();
+        let feature_id = request.entity_query.as_ref()
+            .and_then(|query| query.entity.as_ref())
+            .map(|entity| entity.feature_id.as_str())
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| Status::invalid_argument("missing featureId"))?;
+        let language = request.localization_context.as_ref()
+            .map(|context| context.language.as_str())
+            .unwrap_or("en-US");
+
+        self.store.get(feature_id, language)
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found(format!("{} not found", feature_id)))
+    }
+}
@@ -0,0 +1,210 @@
+use std::convert::Infallible;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use bytes::Bytes;
+use http_body_util::Full;
+use parking_lot::Mutex;
+use hyper::{Request, Response, StatusCode};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{json, Value};
+use crate::faults::{Fault, Faults};
+
+lazy_static! {
+    static ref TILE_REGEX: Regex = Regex::new(r"!1m3!1i(\d+)!2i(\d+)!3i(\d+)").unwrap();
+    static ref LANGUAGE_REGEX: Regex = Regex::new(r"!3m\d+!2s([^!]+)").unwrap();
+    static ref MAP_VERSION_REGEX: Regex = Regex::new(r"!2sm!3i(\d+)").unwrap();
+}
+
+pub(crate) const CAPTCHA_HTML: &str = "<html><head><title>https://maps.googleapis.com/maps/vt</title></head>\
+<body><div>Our systems have detected unusual traffic from your computer network.</div>\
+<form action=\"/sorry/index\" method=\"post\"><div class=\"g-recaptcha\"></div></form></body></html>";
+
+/// A feature served by the fake tile server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticFeature {
+    /// Decimal, as in the real responses. Clients report it in hex.
+    pub id: u64,
+    pub label: String,
+    /// Pixel position within the 256x256 tile.
+    pub anchor: (u32, u32),
+}
+
+impl SyntheticFeature {
+    pub fn hex_id(&self) -> String {
+        format!("{:x}", self.id)
+    }
+}
+
+/// Returns the features the fake server serves for `zoom/x/y`. The same tile
+/// always has the same 0 to 7 features, and IDs don't repeat across tiles.
+pub fn synthetic_features(zoom: usize, x: usize, y: usize) -> Vec<SyntheticFeature> {
+    let seed = splitmix64(((zoom as u64) << 58) ^ ((x as u64) << 29) ^ y as u64);
+
+    (0..seed % 8)
+        .map(|i| {
+            let id = splitmix64(seed.wrapping_add(i + 1));
+            SyntheticFeature {
+                id,
+                label: format!("Feature {}/{}/{} #{}", zoom, x, y, i),
+                anchor: ((id % 256) as u32, ((id >> 8) % 256) as u32),
+            }
+        })
+        .collect()
+}
+
+/// Decodes the `(zoom, x, y)` of every tile in a `pb` parameter.
+pub fn decode_pb(pb: &str) -> Vec<(usize, usize, usize)> {
+    TILE_REGEX.captures_iter(pb)
+        .filter_map(|cap| Some((cap[1].parse().ok()?, cap[2].parse().ok()?, cap[3].parse().ok()?)))
+        .collect()
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn render(pb: &str) -> Value {
+    let language = LANGUAGE_REGEX.captures(pb)
+        .map(|cap| cap[1].to_string())
+        .unwrap_or_else(|| "en-US".to_string());
+
+    let tiles: Vec<Value> = decode_pb(pb).into_iter()
+        .map(|(zoom, x, y)| {
+            let features: Vec<Value> = synthetic_features(zoom, x, y).into_iter()
+                .map(|feature| {
+                    let label = match language.as_str() {
+                        "en-US" => feature.label,
+                        language => format!("{} [{}]", feature.label, language),
+                    };
+                    json!({
+                        "id": feature.id.to_string(),
+                        "a": [feature.anchor.0, feature.anchor.1],
+                        "c": format!("{{1:{{title:{}}}}}", Value::String(label)),
+                    })
+                })
+                .collect();
+            json!({ "id": format!("{}/{}/{}", zoom, x, y), "features": features })
+        })
+        .collect();
+
+    Value::Array(tiles)
+}
+
+/// The map version served until [`MapsTestServer::set_map_version`](crate::MapsTestServer::set_map_version)
+/// retires it. Same as the `maps` crate's default.
+pub const INITIAL_MAP_VERSION: &str = "702451461";
+
+/// A stand-in for the Maps JavaScript API loader, which embeds the tile URL
+/// template and so the current map version.
+fn bootstrap_page(map_version: &str) -> String {
+    format!(
+        "google.maps.Load(function(apiLoad) {{ apiLoad([[[\"https://maps.googleapis.com/maps/vt?pb=!1m5!1m4!1i{{z}}!2i{{x}}!3i{{y}}!4i256!2m3!1e0!2sm!3i{}\"]]]) }});",
+        map_version,
+    )
+}
+
+pub(crate) struct TileService {
+    pub(crate) faults: Faults,
+    pub(crate) map_version: Arc<Mutex<String>>,
+    pub(crate) requests: Arc<AtomicUsize>,
+}
+
+impl TileService {
+    pub(crate) async fn handle(&self, request: Request<hyper::body::Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+
+        let (latency, fault) = self.faults.next();
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        let accept_encoding = request.headers().get(hyper::header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let pb = request.uri().query()
+            .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("pb=")))
+            .map(|pb| pb.to_string());
+
+        let map_version = self.map_version.lock().clone();
+        if request.uri().path() == "/maps/api/js" {
+            return Ok(respond(StatusCode::OK, "text/javascript", bootstrap_page(&map_version), &accept_encoding));
+        }
+
+        let Some(pb) = pb.filter(|_| request.uri().path() == "/maps/vt") else {
+            return Ok(respond(StatusCode::NOT_FOUND, "text/plain", "not found".into(), ""));
+        };
+        let requested_version = MAP_VERSION_REGEX.captures(&pb).map(|cap| cap[1].to_string());
+        if requested_version.is_some_and(|version| version != map_version) {
+            return Ok(respond(StatusCode::NOT_FOUND, "text/plain", "unknown map version".into(), ""));
+        }
+
+        let body = render(&pb).to_string();
+        let (status, content_type, body) = match fault {
+            None => (StatusCode::OK, "application/json", body),
+            Some(Fault::Latency(latency)) => {
+                tokio::time::sleep(latency).await;
+                (StatusCode::OK, "application/json", body)
+            }
+            Some(Fault::RateLimit) => (StatusCode::TOO_MANY_REQUESTS, "text/plain", "rate limited".into()),
+            Some(Fault::ServerError(code)) => (
+                StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                "text/plain",
+                "server error".into(),
+            ),
+            Some(Fault::TruncatedJson) => {
+                let cut = body.len() / 2;
+                (StatusCode::OK, "application/json", body[..cut].to_string())
+            }
+            Some(Fault::Captcha) => (StatusCode::OK, "text/html", CAPTCHA_HTML.to_string()),
+            Some(Fault::XssiPrefix) => (StatusCode::OK, "application/json", format!(")]}}'\n{}", body)),
+        };
+        Ok(respond(status, content_type, body, &accept_encoding))
+    }
+}
+
+/// Builds a response, compressed with the best encoding the client accepts
+/// like the real endpoint does.
+fn respond(status: StatusCode, content_type: &str, body: String, accept_encoding: &str) -> Response<Full<Bytes>> {
+    let accepts = |encoding: &str| accept_encoding.split(',').any(|accepted| accepted.trim() == encoding);
+    let (encoding, body) = if accepts("br") {
+        let mut compressed = Vec::new();
+        let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+        writer.write_all(body.as_bytes()).unwrap();
+        drop(writer);
+        (Some("br"), compressed)
+    } else if accepts("gzip") {
+        let mut writer = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        writer.write_all(body.as_bytes()).unwrap();
+        (Some("gzip"), writer.finish().unwrap())
+    } else {
+        (None, body.into_bytes())
+    };
+
+    let mut response = Response::builder()
+        .status(status)
+        .header("content-type", content_type);
+    if let Some(encoding) = encoding {
+        response = response.header("content-encoding", encoding);
+    }
+    response.body(Full::new(Bytes::from(body))).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_features() {
+        assert_eq!(decode_pb("!1m4!1m3!1i16!2i1!3i2!1m4!1m3!1i16!2i1!3i3!2m3!1e0"), vec![(16, 1, 2), (16, 1, 3)]);
+        assert_eq!(synthetic_features(16, 1, 2), synthetic_features(16, 1, 2));
+
+        let total: usize = (0..16).map(|x| synthetic_features(16, x, 0).len()).sum();
+        assert!(total > 0);
+    }
+}
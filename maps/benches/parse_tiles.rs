@@ -0,0 +1,171 @@
+//! Compares tile response parsing against the previous `serde_json::Value`
+//! based parser.
+//!
+//! Runs against the tile responses of a VCR cassette. Set
+//! `MAPS_BENCH_CASSETTE` to a cassette recorded from the real API; if the
+//! directory doesn't exist yet, it's recorded from the real API around the
+//! Empire State Building first and kept. Without it, a cassette is recorded
+//! from the maps-testkit fake server and deleted afterwards.
+
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use http_body_util::Empty;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use maps::tiles::{Tile, TilesClient};
+use maps::vcr::Vcr;
+use maps_testkit::MapsTestServer;
+
+// Same batch size as locations-rs.
+const TILES_PER_REQUEST: usize = 160;
+const REQUESTS: usize = 20;
+
+fn record_fake_cassette() -> PathBuf {
+    let cassette = std::env::temp_dir().join(format!("maps-bench-cassette-{}", std::process::id()));
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MapsTestServer::start()).unwrap();
+    record_cassette(&runtime, &cassette, &server.tiles_url(), Tile { x: 19000, y: 24600, zoom: 16 });
+    cassette
+}
+
+/// Records `REQUESTS` rows of `TILES_PER_REQUEST` tiles starting at `origin`.
+fn record_cassette(runtime: &tokio::runtime::Runtime, cassette: &Path, base_url: &str, origin: Tile) {
+    runtime.block_on(async {
+        let client = Client::builder(TokioExecutor::new())
+            .build::<_, Empty<Bytes>>(HttpsConnector::new());
+        let client = TilesClient::new(client)
+            .base_url(base_url)
+            .vcr(Vcr::record(cassette).unwrap());
+
+        for request in 0..REQUESTS {
+            let tiles: Vec<Tile> = (0..TILES_PER_REQUEST)
+                .map(|x| Tile { x: origin.x + x, y: origin.y + request, zoom: origin.zoom })
+                .collect();
+            client.view_tiles(&tiles).await.unwrap();
+        }
+    });
+}
+
+fn load_bodies(cassette: &Path) -> Vec<Vec<u8>> {
+    let mut bodies = Vec::new();
+    for entry in std::fs::read_dir(cassette.join("tiles")).unwrap() {
+        let json = std::fs::read(entry.unwrap().path()).unwrap();
+        let entry: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        if entry["status"] == 200 {
+            bodies.push(STANDARD.decode(entry["body"].as_str().unwrap()).unwrap());
+        }
+    }
+    bodies
+}
+
+/// The parser before typed deserialization, kept as the baseline.
+mod value_tree {
+    use lazy_static::lazy_static;
+    use regex::Regex;
+    use serde_json::Value;
+
+    lazy_static! {
+        static ref TITLE_REGEX: Regex = Regex::new(r#"title\\*"?\s*:\s*\\*"((?:[^"\\]|\\.)*)"#).unwrap();
+    }
+
+    /// What `view_tiles` did before: IDs only, from a full `Value` tree.
+    pub fn parse_ids(body: &[u8]) -> Vec<String> {
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+
+        let mut feature_ids = Vec::new();
+        for item in parsed.as_array().into_iter().flatten() {
+            for feature in item["features"].as_array().into_iter().flatten() {
+                if let Some(Ok(id)) = feature["id"].as_str().map(str::parse::<u64>) {
+                    feature_ids.push(format!("{:x}", id));
+                }
+            }
+        }
+        feature_ids
+    }
+
+    pub fn parse_features(body: &[u8]) -> Vec<(String, Option<String>)> {
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let Value::Array(items) = serde_json::from_str(&body).unwrap() else {
+            return Vec::new();
+        };
+
+        let mut features = Vec::new();
+        for item in &items {
+            for feature in item["features"].as_array().into_iter().flatten() {
+                if let Some(Ok(id)) = feature["id"].as_str().map(str::parse::<u64>) {
+                    features.push((format!("{:x}", id), feature_label(feature)));
+                }
+            }
+        }
+        features
+    }
+
+    fn feature_label(feature: &Value) -> Option<String> {
+        let content = feature["c"].as_str()?;
+        if let Ok(parsed) = serde_json::from_str::<Value>(content) {
+            if let Some(title) = find_title(&parsed) {
+                return Some(title);
+            }
+        }
+        let raw = &TITLE_REGEX.captures(content)?[1];
+        let unescaped = raw.replace("\\\"", "\"");
+        Some(serde_json::from_str::<String>(&format!("\"{}\"", unescaped)).unwrap_or(unescaped))
+    }
+
+    fn find_title(value: &Value) -> Option<String> {
+        match value {
+            Value::Object(map) => map.get("title")
+                .and_then(|title| title.as_str())
+                .map(|title| title.to_string())
+                .or_else(|| map.values().find_map(find_title)),
+            Value::Array(items) => items.iter().find_map(find_title),
+            _ => None,
+        }
+    }
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let (cassette, recorded) = match std::env::var_os("MAPS_BENCH_CASSETTE") {
+        Some(cassette) => {
+            let cassette = PathBuf::from(cassette);
+            if !cassette.exists() {
+                // Starts west of the Empire State Building tile, so the rows cross Manhattan
+                let runtime = tokio::runtime::Runtime::new().unwrap();
+                let origin = Tile { x: 19299 - TILES_PER_REQUEST / 2, y: 24631 - REQUESTS / 2, zoom: 16 };
+                record_cassette(&runtime, &cassette, maps::tiles::TILES_API_URL, origin);
+            }
+            (cassette, false)
+        }
+        None => (record_fake_cassette(), true),
+    };
+    let bodies = load_bodies(&cassette);
+    if recorded {
+        std::fs::remove_dir_all(&cassette).unwrap();
+    }
+    assert!(!bodies.is_empty(), "no tile responses in {}", cassette.display());
+
+    let mut group = c.benchmark_group("parse_tiles");
+    group.throughput(Throughput::Bytes(bodies.iter().map(|body| body.len() as u64).sum()));
+
+    group.bench_function(BenchmarkId::new("value_tree", "features"), |b| {
+        b.iter(|| bodies.iter().map(|body| value_tree::parse_features(body).len()).sum::<usize>())
+    });
+    group.bench_function(BenchmarkId::new("typed", "features"), |b| {
+        b.iter(|| bodies.iter().map(|body| maps::tiles::parse_features(body, &[]).unwrap().len()).sum::<usize>())
+    });
+    group.bench_function(BenchmarkId::new("value_tree", "ids"), |b| {
+        b.iter(|| bodies.iter().map(|body| value_tree::parse_ids(body).len()).sum::<usize>())
+    });
+    group.bench_function(BenchmarkId::new("typed", "ids"), |b| {
+        b.iter(|| bodies.iter().map(|body| maps::tiles::parse_ids(body).unwrap().len()).sum::<usize>())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
@@ -11,7 +11,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tonic_build::configure()
         .build_server(false)
-        .compile(&proto_files, &proto_paths)?;
+        .compile_protos(&proto_files, &proto_paths)?;
 
     Ok(())
 }
\ No newline at end of file
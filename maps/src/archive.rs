@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use bytes::{Buf, Bytes, BytesMut};
+use prost::Message;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use tracing::warn;
+use crate::mapsjs::{GetEntityDetailsRequest, GetEntityDetailsResponse};
+
+/// Append-only archive of raw `GetEntityDetails` exchanges.
+///
+/// Each record is the length-delimited encoded request followed by the
+/// length-delimited response body exactly as it came off the wire, so records
+/// can be re-parsed after `mapsjs.proto` gains fields. Call [`flush`](Self::flush)
+/// before dropping the last handle, records still buffered then are lost and
+/// logged as a warning.
+#[derive(Clone)]
+pub struct PlaceArchive {
+    writer: Arc<Mutex<ArchiveWriter>>,
+    failed_writes: Arc<AtomicU64>,
+}
+
+struct ArchiveWriter {
+    file: BufWriter<tokio::fs::File>,
+    path: PathBuf,
+}
+
+impl Drop for ArchiveWriter {
+    fn drop(&mut self) {
+        let unflushed = self.file.buffer().len();
+        if unflushed > 0 {
+            warn!(path = %self.path.display(), unflushed, "place archive dropped without flush, buffered records are lost");
+        }
+    }
+}
+
+impl PlaceArchive {
+    /// Opens `path` for appending, creating it if needed.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+
+        Ok(Self {
+            writer: Arc::new(Mutex::new(ArchiveWriter {
+                file: BufWriter::new(file),
+                path,
+            })),
+            failed_writes: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub async fn write(&self, request: &GetEntityDetailsRequest, response: &[u8]) -> io::Result<()> {
+        let mut record = BytesMut::with_capacity(request.encoded_len() + response.len() + 20);
+        request.encode_length_delimited(&mut record)
+            .map_err(io::Error::other)?;
+        prost::encoding::encode_varint(response.len() as u64, &mut record);
+        record.extend_from_slice(response);
+
+        // One write per record keeps records whole when the archive is shared.
+        let written = self.writer.lock().await.file.write_all(&record).await;
+        if written.is_err() {
+            self.failed_writes.fetch_add(1, Ordering::Relaxed);
+        }
+        written
+    }
+
+    /// Number of records that couldn't be written by this archive and its clones.
+    pub fn failed_writes(&self) -> u64 {
+        self.failed_writes.load(Ordering::Relaxed)
+    }
+
+    pub async fn flush(&self) -> io::Result<()> {
+        self.writer.lock().await.file.flush().await
+    }
+}
+
+/// A single archived exchange.
+#[derive(Debug, Clone)]
+pub struct ArchiveRecord {
+    pub request: GetEntityDetailsRequest,
+    pub response: Bytes,
+}
+
+impl ArchiveRecord {
+    pub fn feature_id(&self) -> Option<&str> {
+        self.request.entity_query.as_ref()?
+            .entity.as_ref()
+            .map(|entity| entity.feature_id.as_str())
+    }
+
+    pub fn decode(&self) -> Result<GetEntityDetailsResponse, prost::DecodeError> {
+        GetEntityDetailsResponse::decode(self.response.clone())
+    }
+}
+
+/// Reads the records written by [`PlaceArchive`] in order.
+pub struct ArchiveReader<R: Read> {
+    reader: R,
+    buffer: BytesMut,
+    eof: bool,
+}
+
+impl ArchiveReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: Read> ArchiveReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    fn next_message(&mut self) -> io::Result<Option<Bytes>> {
+        loop {
+            let mut peek = &self.buffer[..];
+            if let Ok(len) = prost::encoding::decode_varint(&mut peek) {
+                let header = self.buffer.len() - peek.len();
+                if peek.len() >= len as usize {
+                    self.buffer.advance(header);
+                    return Ok(Some(self.buffer.split_to(len as usize).freeze()));
+                }
+            }
+
+            if self.eof {
+                return match self.buffer.is_empty() {
+                    true => Ok(None),
+                    false => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated archive record")),
+                };
+            }
+
+            let mut chunk = [0; 64 * 1024];
+            let read = self.reader.read(&mut chunk)?;
+            if read == 0 {
+                self.eof = true;
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+impl<R: Read> Iterator for ArchiveReader<R> {
+    type Item = io::Result<ArchiveRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let request = match self.next_message() {
+            Ok(Some(request)) => request,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        let response = match self.next_message() {
+            Ok(Some(response)) => response,
+            Ok(None) => return Some(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "missing archived response"))),
+            Err(e) => return Some(Err(e)),
+        };
+
+        Some(GetEntityDetailsRequest::decode(request)
+            .map(|request| ArchiveRecord { request, response })
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapsjs::get_entity_details_request::{EntityQuery, entity_query::Entity};
+
+    #[tokio::test]
+    async fn test_archive_round_trip() {
+        let path = std::env::temp_dir().join(format!("maps-archive-{}.bin", std::process::id()));
+        let request = GetEntityDetailsRequest {
+            entity_query: Some(EntityQuery {
+                entity: Some(Entity { feature_id: "0d134e199a405a163".to_string() }),
+            }),
+            localization_context: None,
+        };
+        // Field 100 isn't in mapsjs.proto and must survive unchanged.
+        let response = [0xa2, 0x06, 0x02, b'h', b'i'];
+
+        let archive = PlaceArchive::open(&path).await.unwrap();
+        archive.write(&request, &response).await.unwrap();
+        archive.write(&request, &[]).await.unwrap();
+        archive.flush().await.unwrap();
+
+        let records: Vec<ArchiveRecord> = ArchiveReader::open(&path).unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].feature_id(), Some("0d134e199a405a163"));
+        assert_eq!(&records[0].response[..], &response);
+        assert!(records[0].decode().is_ok());
+        assert!(records[1].response.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_failed_writes() {
+        let archive = PlaceArchive::open("/dev/full").await.unwrap();
+        // Larger than the write buffer, so it goes straight to the device. The
+        // file writes in the background, so the error shows on the next write.
+        let response = vec![0; 64 * 1024];
+        let request = GetEntityDetailsRequest::default();
+        let results = [archive.write(&request, &response).await, archive.write(&request, &response).await];
+        assert!(results.iter().any(Result::is_err));
+        assert_eq!(archive.clone().failed_writes(), results.iter().filter(|r| r.is_err()).count() as u64);
+    }
+}
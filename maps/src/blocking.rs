@@ -0,0 +1,125 @@
+//! Synchronous wrappers around [`MapsJsInternalClient`] and [`view_tiles`] for
+//! callers that don't run an async runtime, in the spirit of `reqwest::blocking`.
+//!
+//! The client owns a small tokio runtime. Don't make requests from inside an
+//! async context: blocking on the runtime there panics. Dropping the client
+//! anywhere is fine.
+
+// Errors are the same as the async API's, which clippy only flags here.
+#![allow(clippy::result_large_err)]
+
+use std::future::Future;
+use std::sync::Arc;
+use bytes::Bytes;
+use http_body_util::Empty;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client as HttpClient;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use tokio::runtime::Runtime;
+use crate::tiles::{view_tiles, FetchTilesError, Tile};
+use crate::{GetPlaceError, MapsJsInternalClient, MapsJsInternalClientBuilder, MapsJsInternalServiceClientError, Place};
+
+/// Blocking client for place lookups and tile requests. Clones share the same
+/// runtime and connections.
+#[derive(Clone)]
+pub struct Client {
+    runtime: Arc<OwnedRuntime>,
+    places: MapsJsInternalClient,
+    tiles: HttpClient<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+}
+
+/// Shuts the runtime down without waiting for it, since a plain drop blocks
+/// and so panics inside an async context.
+struct OwnedRuntime(Option<Runtime>);
+
+impl OwnedRuntime {
+    fn get(&self) -> &Runtime {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl Drop for OwnedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Client {
+    pub fn new() -> Result<Self, MapsJsInternalServiceClientError> {
+        Self::from_builder(MapsJsInternalClient::builder())
+    }
+
+    /// Creates a client whose place lookups are configured by `builder`.
+    pub fn from_builder(builder: MapsJsInternalClientBuilder) -> Result<Self, MapsJsInternalServiceClientError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("maps-blocking")
+            .enable_all()
+            .build()
+            .map_err(|e| MapsJsInternalServiceClientError::Other(e.to_string()))?;
+
+        let places = runtime.block_on(builder.build())?;
+        let tiles = {
+            let _guard = runtime.enter();
+            HttpClient::builder(TokioExecutor::new())
+                .build::<_, Empty<Bytes>>(HttpsConnector::new())
+        };
+
+        Ok(Self {
+            runtime: Arc::new(OwnedRuntime(Some(runtime))),
+            places,
+            tiles,
+        })
+    }
+
+    /// Blocking version of `get_place(location_id).send()`.
+    pub fn get_place(&self, location_id: impl Into<String>) -> Result<Place, GetPlaceError> {
+        self.block_on(self.places.get_place(location_id.into()).send())
+    }
+
+    /// Blocking version of [`view_tiles`].
+    pub fn view_tiles(&self, tiles: &Vec<Tile>) -> Result<Vec<String>, FetchTilesError> {
+        self.block_on(view_tiles(&self.tiles, tiles))
+    }
+
+    /// The async client, for anything the blocking facade doesn't cover.
+    pub fn inner(&self) -> &MapsJsInternalClient {
+        &self.places
+    }
+
+    /// Runs `future` to completion on the client's runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.get().block_on(future)
+    }
+}
+
+/// Looks up a single place with a one-off client. Reuse a [`Client`] for
+/// more than one lookup.
+pub fn get_place(location_id: impl Into<String>) -> Result<Place, GetPlaceError> {
+    let client = Client::new()
+        .map_err(GetPlaceError::Connect)?;
+    client.get_place(location_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_client_is_shareable() {
+        assert_shareable::<Client>();
+    }
+
+    #[test]
+    fn test_connect_error_keeps_source() {
+        let error = GetPlaceError::Connect(MapsJsInternalServiceClientError::ConnectionFailed("refused".to_string()));
+        let source = std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<MapsJsInternalServiceClientError>());
+        assert!(matches!(source, Some(MapsJsInternalServiceClientError::ConnectionFailed(_))));
+    }
+}
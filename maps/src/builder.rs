@@ -0,0 +1,258 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use bytes::Bytes;
+use hyper::http::uri::PathAndQuery;
+use tonic::{Request, Response, Status};
+use tonic::client::Grpc;
+use tonic::metadata::{Ascii, MetadataKey, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tower::discover::Change;
+use crate::*;
+use crate::archive::PlaceArchive;
+use crate::codec::RawCodec;
+use crate::grpc_web::{GrpcWebClient, GrpcWebMode};
+use crate::mapsjs::GetEntityDetailsRequest;
+use crate::vcr::{Vcr, VcrClient, VcrMode};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Adds the configured metadata to every outgoing request.
+#[derive(Clone, Default)]
+pub struct MetadataInterceptor {
+    metadata: Arc<Vec<(MetadataKey<Ascii>, MetadataValue<Ascii>)>>,
+}
+
+impl Interceptor for MetadataInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        for (key, value) in self.metadata.iter() {
+            request.metadata_mut().insert(key.clone(), value.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// Wire protocol used to reach `MapsJsInternalService`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transport {
+    /// Native gRPC over HTTP/2.
+    #[default]
+    Grpc,
+    /// gRPC-web over HTTP/1.1, for proxies that don't pass HTTP/2.
+    GrpcWeb(GrpcWebMode),
+}
+
+pub(crate) const GET_ENTITY_DETAILS_PATH: &str = "/google.internal.maps.mapsjs.v1.MapsJsInternalService/GetEntityDetails";
+
+#[derive(Clone)]
+pub(crate) enum ServiceClient {
+    Grpc(Grpc<InterceptedService<Channel, MetadataInterceptor>>),
+    GrpcWeb(Box<GrpcWebClient>),
+    Vcr(Box<VcrClient>),
+}
+
+impl ServiceClient {
+    /// Calls `GetEntityDetails` and returns the undecoded response message.
+    pub(crate) async fn get_entity_details(
+        &mut self,
+        request: Request<GetEntityDetailsRequest>,
+    ) -> Result<Response<Bytes>, Status> {
+        match self {
+            ServiceClient::Grpc(client) => {
+                client.ready().await
+                    .map_err(|e| Status::unknown(format!("Service was not ready: {}", e)))?;
+                let path = PathAndQuery::from_static(GET_ENTITY_DETAILS_PATH);
+                client.unary(request, path, RawCodec::default()).await
+            }
+            ServiceClient::GrpcWeb(client) => client.get_entity_details(request).await,
+            ServiceClient::Vcr(client) => client.get_entity_details(request).await,
+        }
+    }
+}
+
+/// Builder for [`MapsJsInternalClient`], created with [`MapsJsInternalClient::builder`].
+pub struct MapsJsInternalClientBuilder {
+    url: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    keep_alive_interval: Option<Duration>,
+    keep_alive_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    metadata: Vec<(String, String)>,
+    pool_size: usize,
+    transport: Transport,
+    archive: Option<PlaceArchive>,
+    vcr: Option<Vcr>,
+}
+
+impl Default for MapsJsInternalClientBuilder {
+    fn default() -> Self {
+        Self {
+            url: MAPS_API_URL.to_string(),
+            timeout: Some(DEFAULT_TIMEOUT),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            keep_alive_interval: None,
+            keep_alive_timeout: None,
+            user_agent: None,
+            metadata: Vec::new(),
+            pool_size: 1,
+            transport: Transport::Grpc,
+            archive: None,
+            vcr: None,
+        }
+    }
+}
+
+impl MapsJsInternalClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the service URL, mostly useful for tests.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Deadline applied to every request. Defaults to 30 seconds, `None` disables it.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Deadline for establishing a connection. Defaults to 10 seconds, `None` disables it.
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sends HTTP/2 keepalive pings at `interval` and drops the connection if no
+    /// ack arrives within `timeout`.
+    pub fn keep_alive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keep_alive_interval = Some(interval);
+        self.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Adds a gRPC metadata entry (e.g. `referer` or `x-goog-api-key`) to every request.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Selects native gRPC (the default) or gRPC-web. Keepalive and pooling only
+    /// apply to native gRPC, building a gRPC-web client with either fails.
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Writes every raw response to `archive`, see [`PlaceArchive`]. Lookups
+    /// don't fail when a write does, see [`PlaceArchive::failed_writes`].
+    pub fn archive(mut self, archive: PlaceArchive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Records to or replays from a cassette, see [`Vcr`]. When replaying,
+    /// the client never connects.
+    pub fn vcr(mut self, vcr: Vcr) -> Self {
+        self.vcr = Some(vcr);
+        self
+    }
+
+    /// Number of connections to balance requests over, see [`MapsJsInternalClient::pooled`].
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.pool_size = size.max(1);
+        self
+    }
+
+    pub async fn build(self) -> Result<MapsJsInternalClient, MapsJsInternalServiceClientError> {
+        let mut metadata = Vec::with_capacity(self.metadata.len());
+        for (key, value) in &self.metadata {
+            metadata.push((
+                MetadataKey::from_str(key)?,
+                MetadataValue::from_str(value)?,
+            ));
+        }
+        let interceptor = MetadataInterceptor {
+            metadata: Arc::new(metadata),
+        };
+
+        if let Transport::GrpcWeb(mode) = self.transport {
+            if self.keep_alive_interval.is_some() || self.keep_alive_timeout.is_some() {
+                return Err(MapsJsInternalServiceClientError::Other("keepalive is not supported with the gRPC-web transport".to_string()));
+            }
+            if self.pool_size > 1 {
+                return Err(MapsJsInternalServiceClientError::Other("pool_size is not supported with the gRPC-web transport".to_string()));
+            }
+            let client = GrpcWebClient::new(self.url, mode, self.timeout, self.connect_timeout, self.user_agent, interceptor);
+            let client = MapsJsInternalClient {
+                client: ServiceClient::GrpcWeb(Box::new(client)),
+                archive: self.archive,
+            };
+            return Ok(match self.vcr {
+                Some(vcr) => client.with_vcr(vcr),
+                None => client,
+            });
+        }
+
+        let endpoint = self.endpoint()?;
+        let replaying = self.vcr.as_ref().is_some_and(|vcr| vcr.mode() == VcrMode::Replay);
+        let channel = if replaying {
+            endpoint.connect_lazy()
+        } else if self.pool_size > 1 {
+            // Pooled connections are opened lazily, so this one only checks the
+            // endpoint is reachable, same as building a single connection does
+            endpoint.connect().await?;
+            let (channel, tx) = Channel::balance_channel(self.pool_size);
+            for i in 0..self.pool_size {
+                tx.try_send(Change::Insert(i, endpoint.clone()))
+                    .map_err(|e| MapsJsInternalServiceClientError::ConnectionFailed(e.to_string()))?;
+            }
+            channel
+        } else {
+            endpoint.connect().await?
+        };
+
+        let mut client = MapsJsInternalClient::with_interceptor(channel, interceptor);
+        client.archive = self.archive;
+        Ok(match self.vcr {
+            Some(vcr) => client.with_vcr(vcr),
+            None => client,
+        })
+    }
+
+    fn endpoint(&self) -> Result<Endpoint, tonic::transport::Error> {
+        let mut endpoint = Endpoint::from_shared(self.url.clone())?;
+        if endpoint.uri().scheme_str() == Some("https") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        if let Some(timeout) = self.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        if let Some(interval) = self.keep_alive_interval {
+            endpoint = endpoint.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.keep_alive_timeout {
+            endpoint = endpoint
+                .keep_alive_timeout(timeout)
+                .keep_alive_while_idle(true);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            endpoint = endpoint.user_agent(user_agent.clone())?;
+        }
+        Ok(endpoint)
+    }
+}
@@ -0,0 +1,212 @@
+use std::io::Write;
+use serde_json::{json, Value};
+use thiserror::Error;
+use crate::Place;
+
+/// Column order of [`CsvWriter`], matching the field order of [`Place`].
+pub const CSV_COLUMNS: [&str; 12] = [
+    "location_id",
+    "title",
+    "local_language_title",
+    "rating",
+    "phone",
+    "url",
+    "menu_url",
+    "global_code",
+    "compound_code",
+    "altitude",
+    "longitude",
+    "latitude",
+];
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+}
+
+/// Writes places to an output format. `finish` must be called once all places
+/// have been written.
+pub trait PlaceWriter {
+    fn write_place(&mut self, place: &Place) -> Result<(), ExportError>;
+
+    fn finish(&mut self) -> Result<(), ExportError>;
+}
+
+/// One JSON object per line.
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> PlaceWriter for JsonLinesWriter<W> {
+    fn write_place(&mut self, place: &Place) -> Result<(), ExportError> {
+        serde_json::to_writer(&mut self.writer, place)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExportError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// CSV with a header row, see [`CSV_COLUMNS`] for the column order.
+pub struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(writer),
+        }
+    }
+}
+
+impl<W: Write> PlaceWriter for CsvWriter<W> {
+    fn write_place(&mut self, place: &Place) -> Result<(), ExportError> {
+        self.writer.serialize(place)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExportError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A GeoJSON `FeatureCollection` of point features. Places without a location
+/// are skipped.
+pub struct GeoJsonWriter<W: Write> {
+    writer: W,
+    written: usize,
+}
+
+impl<W: Write> GeoJsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, written: 0 }
+    }
+}
+
+impl<W: Write> PlaceWriter for GeoJsonWriter<W> {
+    fn write_place(&mut self, place: &Place) -> Result<(), ExportError> {
+        let Some(feature) = to_geojson_feature(place) else {
+            return Ok(());
+        };
+
+        if self.written == 0 {
+            self.writer.write_all(br#"{"type":"FeatureCollection","features":["#)?;
+        } else {
+            self.writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.writer, &feature)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ExportError> {
+        if self.written == 0 {
+            self.writer.write_all(br#"{"type":"FeatureCollection","features":["#)?;
+        }
+        self.writer.write_all(b"]}\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Converts a place into a GeoJSON point `Feature` with the remaining fields as
+/// properties.
+pub fn to_geojson_feature(place: &Place) -> Option<Value> {
+    let (lat, lng) = place.lat_lng()?;
+
+    let mut properties = serde_json::to_value(place).ok()?;
+    if let Some(properties) = properties.as_object_mut() {
+        properties.remove("latitude");
+        properties.remove("longitude");
+    }
+
+    Some(json!({
+        "type": "Feature",
+        "id": place.location_id,
+        "geometry": {
+            "type": "Point",
+            "coordinates": [lng, lat],
+        },
+        "properties": properties,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place() -> Place {
+        Place {
+            location_id: "0d134e199a405a163".to_string(),
+            title: Some("Empire State Building".to_string()),
+            local_language_title: None,
+            rating: Some(4),
+            phone: Some("(212) 736-3100".to_string()),
+            url: None,
+            menu_url: None,
+            global_code: Some("87G8Q257+9P".to_string()),
+            compound_code: None,
+            altitude: None,
+            longitude: Some(-739856644),
+            latitude: Some(407484405),
+        }
+    }
+
+    #[test]
+    fn test_csv_columns() {
+        let mut writer = CsvWriter::new(Vec::new());
+        writer.write_place(&place()).unwrap();
+        writer.finish().unwrap();
+        let output = String::from_utf8(writer.writer.into_inner().unwrap()).unwrap();
+
+        let mut lines = output.lines();
+        assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "0d134e199a405a163,Empire State Building,,4,(212) 736-3100,,,87G8Q257+9P,,,-739856644,407484405"
+        );
+    }
+
+    #[test]
+    fn test_geojson_feature_collection() {
+        let mut writer = GeoJsonWriter::new(Vec::new());
+        writer.write_place(&place()).unwrap();
+        writer.write_place(&Place { latitude: None, ..place() }).unwrap();
+        writer.finish().unwrap();
+
+        let collection: Value = serde_json::from_slice(&writer.writer).unwrap();
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0]["geometry"]["coordinates"], json!([-73.9856644, 40.7484405]));
+        assert_eq!(features[0]["properties"]["title"], "Empire State Building");
+    }
+
+    #[test]
+    fn test_json_lines_round_trip() {
+        let mut writer = JsonLinesWriter::new(Vec::new());
+        writer.write_place(&place()).unwrap();
+        writer.finish().unwrap();
+
+        let line = String::from_utf8(writer.writer).unwrap();
+        let parsed: Place = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed, place());
+    }
+}
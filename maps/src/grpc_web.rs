@@ -0,0 +1,316 @@
+use std::time::Duration;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, StatusCode};
+use hyper::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT};
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use prost::Message;
+use tonic::{Code, Request, Response, Status};
+use tonic::metadata::MetadataMap;
+use tonic::service::Interceptor;
+use crate::builder::{MetadataInterceptor, GET_ENTITY_DETAILS_PATH};
+use crate::mapsjs::GetEntityDetailsRequest;
+const TRAILER_FLAG: u8 = 0x80;
+
+/// Framing used by the gRPC-web transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrpcWebMode {
+    /// `application/grpc-web+proto`, length-prefixed binary frames.
+    Binary,
+    /// `application/grpc-web-text`, the same frames base64 encoded.
+    Text,
+}
+
+impl GrpcWebMode {
+    fn content_type(self) -> &'static str {
+        match self {
+            GrpcWebMode::Binary => "application/grpc-web+proto",
+            GrpcWebMode::Text => "application/grpc-web-text",
+        }
+    }
+}
+
+/// Calls `MapsJsInternalService` over gRPC-web on plain HTTP/1.1, the way the
+/// Maps JS frontend does.
+#[derive(Clone)]
+pub(crate) struct GrpcWebClient {
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+    url: String,
+    mode: GrpcWebMode,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    interceptor: MetadataInterceptor,
+}
+
+impl GrpcWebClient {
+    pub(crate) fn new(
+        url: String,
+        mode: GrpcWebMode,
+        timeout: Option<Duration>,
+        connect_timeout: Option<Duration>,
+        user_agent: Option<String>,
+        interceptor: MetadataInterceptor,
+    ) -> Self {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_connect_timeout(connect_timeout);
+        let client = Client::builder(TokioExecutor::new())
+            .build(HttpsConnector::new_with_connector(http));
+
+        Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            mode,
+            timeout,
+            user_agent,
+            interceptor,
+        }
+    }
+
+    pub(crate) async fn get_entity_details(
+        &mut self,
+        request: Request<GetEntityDetailsRequest>,
+    ) -> Result<Response<Bytes>, Status> {
+        let (metadata, extensions, message) = request.into_parts();
+        let request = self.interceptor.call(Request::from_parts(metadata, extensions, ()))?;
+        let timeout = grpc_timeout(request.metadata()).or(self.timeout);
+
+        let mut http_request = hyper::Request::builder()
+            .method(Method::POST)
+            .uri(format!("{}{}", self.url, GET_ENTITY_DETAILS_PATH))
+            .body(Full::new(encode_request(&message, self.mode)))
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let headers = http_request.headers_mut();
+        headers.extend(request.into_parts().0.into_headers());
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(self.mode.content_type()));
+        headers.insert(ACCEPT, HeaderValue::from_static(self.mode.content_type()));
+        headers.insert("x-grpc-web", HeaderValue::from_static("1"));
+        if let Some(user_agent) = &self.user_agent {
+            let value = HeaderValue::from_str(user_agent)
+                .map_err(|e| Status::internal(e.to_string()))?;
+            headers.insert(USER_AGENT, value);
+        }
+
+        let call = self.call(http_request);
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, call).await
+                .map_err(|_| Status::deadline_exceeded("gRPC-web request timed out"))?,
+            None => call.await,
+        }
+    }
+
+    async fn call(
+        &self,
+        request: hyper::Request<Full<Bytes>>,
+    ) -> Result<Response<Bytes>, Status> {
+        let response = self.client.request(request).await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        let (parts, body) = response.into_parts();
+        if parts.status != StatusCode::OK {
+            return Err(Status::new(http_status_to_code(parts.status), format!("HTTP status {}", parts.status)));
+        }
+        // Trailers-only responses carry the status in the headers.
+        if let Some(status) = status_from_headers(&parts.headers) {
+            return Err(status);
+        }
+
+        let body = body.collect().await
+            .map_err(|e| Status::unavailable(e.to_string()))?
+            .to_bytes();
+        let frames = decode_frames(body, self.mode).map_err(Status::internal)?;
+
+        let mut message = None;
+        for frame in frames {
+            match frame {
+                Frame::Message(data) => message = Some(data),
+                Frame::Trailers(trailers) => {
+                    if let Some(status) = status_from_headers(&trailers) {
+                        return Err(status);
+                    }
+                }
+            }
+        }
+
+        let message = message.ok_or_else(|| Status::internal("Missing response message"))?;
+        Ok(Response::from_parts(MetadataMap::from_headers(parts.headers), message, Default::default()))
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    Message(Bytes),
+    Trailers(HeaderMap),
+}
+
+fn encode_request(message: &GetEntityDetailsRequest, mode: GrpcWebMode) -> Bytes {
+    let mut frame = BytesMut::with_capacity(5 + message.encoded_len());
+    frame.put_u8(0);
+    frame.put_u32(message.encoded_len() as u32);
+    message.encode(&mut frame).expect("buffer has enough capacity");
+
+    match mode {
+        GrpcWebMode::Binary => frame.freeze(),
+        GrpcWebMode::Text => Bytes::from(STANDARD.encode(frame)),
+    }
+}
+
+fn decode_frames(body: Bytes, mode: GrpcWebMode) -> Result<Vec<Frame>, String> {
+    let mut body = match mode {
+        GrpcWebMode::Binary => body,
+        GrpcWebMode::Text => decode_text(&body)?,
+    };
+
+    let mut frames = Vec::new();
+    while body.has_remaining() {
+        if body.remaining() < 5 {
+            return Err("Truncated gRPC-web frame header".to_string());
+        }
+        let flag = body.get_u8();
+        let len = body.get_u32() as usize;
+        if body.remaining() < len {
+            return Err("Truncated gRPC-web frame".to_string());
+        }
+        let data = body.split_to(len);
+
+        if flag & TRAILER_FLAG != 0 {
+            frames.push(Frame::Trailers(parse_trailers(&data)));
+        } else {
+            frames.push(Frame::Message(data));
+        }
+    }
+
+    Ok(frames)
+}
+
+// Each frame may be base64 encoded on its own, so padding can appear mid-body.
+fn decode_text(body: &[u8]) -> Result<Bytes, String> {
+    let text: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+
+    let mut start = 0;
+    for (i, chunk) in text.chunks(4).enumerate() {
+        if chunk.ends_with(b"=") {
+            let end = i * 4 + chunk.len();
+            decoded.extend(STANDARD.decode(&text[start..end])
+                .map_err(|e| format!("Invalid base64 body: {}", e))?);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        decoded.extend(STANDARD.decode(&text[start..])
+            .map_err(|e| format!("Invalid base64 body: {}", e))?);
+    }
+
+    Ok(Bytes::from(decoded))
+}
+
+fn parse_trailers(data: &[u8]) -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    for line in String::from_utf8_lossy(data).split("\r\n") {
+        if let Some((key, value)) = line.split_once(':') {
+            if let (Ok(key), Ok(value)) = (
+                hyper::header::HeaderName::from_bytes(key.trim().to_ascii_lowercase().as_bytes()),
+                HeaderValue::from_str(value.trim()),
+            ) {
+                trailers.insert(key, value);
+            }
+        }
+    }
+    trailers
+}
+
+fn status_from_headers(headers: &HeaderMap) -> Option<Status> {
+    let code = headers.get("grpc-status")?.to_str().ok()?.parse::<i32>().ok()?;
+    if code == 0 {
+        return None;
+    }
+    let message = headers.get("grpc-message")
+        .and_then(|m| m.to_str().ok())
+        .and_then(|m| urlencoding::decode(m).ok())
+        .map(|m| m.into_owned())
+        .unwrap_or_default();
+    Some(Status::new(Code::from_i32(code), message))
+}
+
+fn grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get("grpc-timeout")?.to_str().ok()?;
+    let (amount, unit) = value.split_at(value.len().checked_sub(1)?);
+    let amount: u64 = amount.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 60 * 60)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+// Keeps the rate limit and not found mapping of native gRPC intact.
+fn http_status_to_code(status: StatusCode) -> Code {
+    match status {
+        StatusCode::BAD_REQUEST => Code::Internal,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => Code::Unavailable,
+        _ => Code::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapsjs::GetEntityDetailsResponse;
+
+    fn response_body() -> Vec<u8> {
+        let message = GetEntityDetailsResponse::default().encode_to_vec();
+        let trailers = b"grpc-status: 0\r\ngrpc-message: \r\n";
+
+        let mut body = vec![0];
+        body.extend((message.len() as u32).to_be_bytes());
+        body.extend(&message);
+        body.push(TRAILER_FLAG);
+        body.extend((trailers.len() as u32).to_be_bytes());
+        body.extend(trailers);
+        body
+    }
+
+    #[test]
+    fn test_decode_frames() {
+        let frames = decode_frames(Bytes::from(response_body()), GrpcWebMode::Binary).unwrap();
+        assert!(matches!(frames[0], Frame::Message(_)));
+        match &frames[1] {
+            Frame::Trailers(trailers) => assert_eq!(trailers["grpc-status"], "0"),
+            other => panic!("expected trailers, got {:?}", other),
+        }
+
+        // Text mode may encode each frame separately.
+        let body = response_body();
+        let (message, trailers) = body.split_at(5);
+        let text = format!("{}{}", STANDARD.encode(message), STANDARD.encode(trailers));
+        let frames = decode_frames(Bytes::from(text), GrpcWebMode::Text).unwrap();
+        assert_eq!(frames.len(), 2);
+
+        assert!(decode_frames(Bytes::from(vec![0, 0, 0, 0, 9, 1]), GrpcWebMode::Binary).is_err());
+    }
+
+    #[test]
+    fn test_status_from_trailers() {
+        let trailers = parse_trailers(b"grpc-status: 5\r\ngrpc-message: no%20such%20place\r\n");
+        let status = status_from_headers(&trailers).unwrap();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "no such place");
+
+        assert!(status_from_headers(&parse_trailers(b"grpc-status: 0\r\n")).is_none());
+    }
+}
@@ -1,17 +1,29 @@
 use thiserror::Error;
-use tonic::transport::{Channel, ClientTlsConfig};
+use tonic::transport::Channel;
+use tonic::service::interceptor::InterceptedService;
+use futures::{Stream, StreamExt};
 pub mod tiles;
+pub mod export;
+pub mod pluscode;
+pub mod url;
+pub mod blocking;
+pub mod vcr;
 mod places;
-
-use mapsjs::maps_js_internal_service_client::MapsJsInternalServiceClient;
-pub use places::{Place, GetPlaceError};
-
-mod mapsjs {
+mod builder;
+mod grpc_web;
+mod codec;
+mod archive;
+
+pub use places::{Place, GetPlaceError, GetPlaceRequest, MissingFieldError, RawPlaceResponse};
+pub use archive::{PlaceArchive, ArchiveReader, ArchiveRecord};
+pub use builder::{MapsJsInternalClientBuilder, MetadataInterceptor, Transport};
+pub use grpc_web::GrpcWebMode;
+use builder::ServiceClient;
+
+pub mod mapsjs {
     tonic::include_proto!("google.internal.maps.mapsjs.v1");
 }
 
-use places::GetPlaceRequest;
-
 #[derive(Error, Debug)]
 pub enum MapsJsInternalServiceClientError {
     #[error("Tonic transport error: {0}")]
@@ -23,6 +35,9 @@ pub enum MapsJsInternalServiceClientError {
     #[error("Invalid metadata value: {0}")]
     InvalidMetadata(#[from] tonic::metadata::errors::InvalidMetadataValue),
 
+    #[error("Invalid metadata key: {0}")]
+    InvalidMetadataKey(#[from] tonic::metadata::errors::InvalidMetadataKey),
+
     #[error("Request failed: {0}")]
     RequestFailed(String),
 
@@ -57,37 +72,117 @@ pub enum RequestError {
     Other(String),
 }
 
-
+#[derive(Clone)]
 pub struct MapsJsInternalClient {
-    client: MapsJsInternalServiceClient<Channel>,
+    client: ServiceClient,
+    archive: Option<PlaceArchive>,
 }
 
+const MAPS_API_URL: &str = "https://maps.googleapis.com";
+pub const DEFAULT_LANGUAGE: &str = "en-US";
+pub const DEFAULT_REGION: &str = "US";
+
 impl MapsJsInternalClient {
     pub async fn new() -> Result<Self, MapsJsInternalServiceClientError> {
-        let channel = Channel::from_static("https://maps.googleapis.com")
-            .tls_config(ClientTlsConfig::new())?
-            .connect()
-            .await?;
+        Self::builder().build().await
+    }
 
-        let client = MapsJsInternalServiceClient::new(channel);
+    pub fn builder() -> MapsJsInternalClientBuilder {
+        MapsJsInternalClientBuilder::new()
+    }
 
-        Ok(Self {
-            client,
-        })
+    /// Creates a client that spreads requests over `size` separate connections.
+    ///
+    /// A single HTTP/2 connection caps the number of concurrent streams, so bulk
+    /// lookups should use a pool. Fails if the endpoint can't be reached.
+    /// Connections are opened lazily, balanced with power-of-two-choices and
+    /// re-established if they fail.
+    pub async fn pooled(size: usize) -> Result<Self, MapsJsInternalServiceClientError> {
+        Self::builder().pool_size(size).build().await
     }
 
     pub async fn from_channel(channel: Channel) -> Result<Self, MapsJsInternalServiceClientError> {
-        let client = MapsJsInternalServiceClient::new(channel);
+        Ok(Self::with_interceptor(channel, MetadataInterceptor::default()))
+    }
 
-        Ok(Self {
-            client,
-        })
+    fn with_interceptor(channel: Channel, interceptor: MetadataInterceptor) -> Self {
+        let client = tonic::client::Grpc::new(InterceptedService::new(channel, interceptor));
+
+        Self {
+            client: ServiceClient::Grpc(client),
+            archive: None,
+        }
     }
 
-    pub fn get_place(&mut self, location_id: String) -> GetPlaceRequest {
+    /// Records every lookup to, or answers every lookup from, a cassette.
+    /// See [`vcr`].
+    pub fn with_vcr(mut self, vcr: vcr::Vcr) -> Self {
+        self.client = ServiceClient::Vcr(Box::new(vcr::VcrClient {
+            inner: self.client,
+            vcr,
+        }));
+        self
+    }
+
+    pub fn get_place(&self, location_id: String) -> GetPlaceRequest {
         GetPlaceRequest {
-            client: &mut self.client,
-            location_id
+            client: self.client.clone(),
+            location_id,
+            timeout: None,
+            language: DEFAULT_LANGUAGE.to_string(),
+            region: DEFAULT_REGION.to_string(),
+            archive: self.archive.clone(),
         }
     }
+
+    /// Looks up every ID from `ids`, keeping at most `concurrency` requests in flight.
+    /// Results are yielded in input order and a failed lookup does not end the stream.
+    pub fn get_places<S>(&self, ids: S, concurrency: usize) -> impl Stream<Item = (String, Result<Place, GetPlaceError>)> + Send + 'static
+    where
+        S: Stream<Item = String> + Send + 'static,
+    {
+        let client = self.clone();
+        ids.map(move |id| client.lookup(id))
+            .buffered(concurrency.max(1))
+    }
+
+    /// Same as [`get_places`](Self::get_places), but yields results as soon as they complete.
+    pub fn get_places_unordered<S>(&self, ids: S, concurrency: usize) -> impl Stream<Item = (String, Result<Place, GetPlaceError>)> + Send + 'static
+    where
+        S: Stream<Item = String> + Send + 'static,
+    {
+        let client = self.clone();
+        ids.map(move |id| client.lookup(id))
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    fn lookup(&self, location_id: String) -> impl std::future::Future<Output = (String, Result<Place, GetPlaceError>)> + Send + 'static {
+        let request = self.get_place(location_id.clone());
+        async move { (location_id, request.send().await) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+    #[test]
+    fn client_is_shareable() {
+        assert_shareable::<MapsJsInternalClient>();
+    }
+
+    // Only needs to compile: the request future must be spawnable on its own.
+    #[allow(dead_code)]
+    fn get_place_is_spawnable(client: &MapsJsInternalClient) {
+        tokio::spawn(client.get_place("0d134e199a405a163".to_string()).send());
+    }
+
+    #[allow(dead_code)]
+    fn get_places_is_spawnable(client: &MapsJsInternalClient) {
+        let ids = futures::stream::iter(vec!["0d134e199a405a163".to_string()]);
+        let results = client.get_places_unordered(ids, 8);
+        tokio::spawn(async move { results.collect::<Vec<_>>().await });
+    }
 }
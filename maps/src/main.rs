@@ -0,0 +1,218 @@
+use std::process;
+use bytes::Bytes;
+use http_body_util::Empty;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use maps::tiles::{view_tile_features, Tile};
+use maps::url::{self, FeatureId};
+use maps::{pluscode, MapsJsInternalClient, DEFAULT_LANGUAGE, DEFAULT_REGION};
+use serde_json::{json, Value};
+
+const USAGE: &str = "\
+Usage: maps [--lang LANG] [--region REGION] [--json] <command>
+
+Commands:
+  tile <zoom/x/y>     List the feature IDs and labels in a tile
+  place <id>          Look up a place and print its details
+  convert <input>     Convert between formats. <input> is one of:
+                        zoom/x/y              a tile
+                        lat,lng[,zoom]        a point (zoom defaults to 16)
+                        0d134e199a405a163     a location ID (hex)
+                        0x0:0xd134e199a405a163  a feature ID
+                        cid:15074921902713971043  a cid (decimal)
+                        https://...           a Google Maps URL
+
+Options:
+  --lang LANG         Language of labels and details (default: en-US)
+  --region REGION     Region to request from (default: US)
+  --json              Print JSON instead of text";
+
+const DEFAULT_ZOOM: usize = 16;
+
+struct Options {
+    language: String,
+    region: String,
+    json: bool,
+    command: String,
+    argument: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let options = parse_args(std::env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    let result = match options.command.as_str() {
+        "tile" => tile(&options).await,
+        "place" => place(&options).await,
+        "convert" => convert(&options),
+        _ => unreachable!(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut language = DEFAULT_LANGUAGE.to_string();
+    let mut region = DEFAULT_REGION.to_string();
+    let mut json = false;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => language = args.next().ok_or("--lang needs a value")?,
+            "--region" => region = args.next().ok_or("--region needs a value")?,
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            _ => positional.push(arg),
+        }
+    }
+
+    match &positional[..] {
+        [command, argument] if ["tile", "place", "convert"].contains(&command.as_str()) => Ok(Options {
+            language,
+            region,
+            json,
+            command: command.clone(),
+            argument: argument.clone(),
+        }),
+        [command, ..] if !["tile", "place", "convert"].contains(&command.as_str()) => Err(format!("Unknown command {}", command)),
+        _ => Err("Expected a command and one argument".to_string()),
+    }
+}
+
+async fn tile(options: &Options) -> Result<(), String> {
+    let tile: Tile = options.argument.parse()?;
+    let client = Client::builder(TokioExecutor::new())
+        .build::<_, Empty<Bytes>>(HttpsConnector::new());
+
+    let features = view_tile_features(&client, &vec![tile], &options.language, &options.region).await
+        .map_err(|e| e.to_string())?;
+
+    if options.json {
+        print_json(&json!(features));
+    } else {
+        for feature in features {
+            println!("{}\t{}", feature.id, feature.label.unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+async fn place(options: &Options) -> Result<(), String> {
+    let client = MapsJsInternalClient::new().await
+        .map_err(|e| e.to_string())?;
+
+    let place = client.get_place(options.argument.clone())
+        .language(options.language.clone())
+        .region(options.region.clone())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let Value::Object(fields) = json!(place) else {
+        unreachable!("places serialize to objects");
+    };
+    print_fields(options, fields.into_iter().collect());
+    Ok(())
+}
+
+fn convert(options: &Options) -> Result<(), String> {
+    let input = options.argument.trim();
+
+    let fields = if input.contains("://") || input.starts_with("/url?") {
+        let parsed = url::parse(input).map_err(|e| e.to_string())?;
+        let mut fields = match parsed.feature_id {
+            Some(feature_id) => feature_fields(feature_id),
+            None => Vec::new(),
+        };
+        if let Some(title) = parsed.title {
+            fields.push(("title", json!(title)));
+        }
+        if let (Some(lat), Some(lng)) = (parsed.latitude, parsed.longitude) {
+            let zoom = parsed.zoom.map(|zoom| zoom as usize).unwrap_or(DEFAULT_ZOOM);
+            fields.extend(point_fields(lat, lng, zoom)?);
+        }
+        fields
+    } else if let Some(cid) = input.strip_prefix("cid:") {
+        let id = cid.trim().parse().map_err(|_| format!("Invalid cid {}", cid))?;
+        feature_fields(FeatureId { cell_id: 0, id })
+    } else if input.contains(':') {
+        feature_fields(input.parse().map_err(|e: url::UrlError| e.to_string())?)
+    } else if input.contains('/') {
+        let tile: Tile = input.parse()?;
+        let (lat, lng) = tile.center();
+        let mut fields = vec![("tile", json!(tile.to_string()))];
+        fields.push(("center", json!([lat, lng])));
+        fields.push(("plus_code", json!(pluscode::encode(lat, lng, pluscode::DEFAULT_CODE_LENGTH).map_err(|e| e.to_string())?)));
+        fields
+    } else if input.contains(',') {
+        let parts: Vec<f64> = input.split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid point {}: {}", input, e))?;
+        match parts[..] {
+            [lat, lng] => point_fields(lat, lng, DEFAULT_ZOOM)?,
+            [lat, lng, zoom] => point_fields(lat, lng, zoom as usize)?,
+            _ => return Err(format!("Invalid point {}: expected lat,lng[,zoom]", input)),
+        }
+    } else {
+        // Bare digits are a hex location ID, as written by locations-rs
+        feature_fields(FeatureId::from_location_id(input).map_err(|e| e.to_string())?)
+    };
+
+    print_fields(options, fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect());
+    Ok(())
+}
+
+/// Prints `fields` as a JSON object, or as `key<TAB>value` lines leaving out
+/// unknown values.
+fn print_fields(options: &Options, fields: Vec<(String, Value)>) {
+    if options.json {
+        print_json(&Value::Object(fields.into_iter().collect()));
+        return;
+    }
+    for (key, value) in fields {
+        let value = match value {
+            Value::Null => continue,
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+        println!("{}\t{}", key, value);
+    }
+}
+
+fn feature_fields(feature_id: FeatureId) -> Vec<(&'static str, Value)> {
+    vec![
+        ("location_id", json!(feature_id.location_id())),
+        ("feature_id", json!(feature_id.to_string())),
+        ("cid", json!(feature_id.cid().to_string())),
+        ("url", json!(format!("https://maps.google.com/?cid={}", feature_id.cid()))),
+    ]
+}
+
+fn point_fields(lat: f64, lng: f64, zoom: usize) -> Result<Vec<(&'static str, Value)>, String> {
+    if zoom > 22 {
+        return Err(format!("Zoom {} is out of range", zoom));
+    }
+    Ok(vec![
+        ("lat_lng", json!([lat, lng])),
+        ("tile", json!(Tile::from_lat_lng(lat, lng, zoom)?.to_string())),
+        ("plus_code", json!(pluscode::encode(lat, lng, pluscode::DEFAULT_CODE_LENGTH).map_err(|e| e.to_string())?)),
+    ])
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
@@ -1,36 +1,54 @@
 use std::str::FromStr;
+use std::time::Duration;
 use thiserror::Error;
 use crate::*;
 use tonic::Request;
-use tonic::metadata::MetadataValue;
-use tonic::transport::Channel;
-use regex::Regex;
-use urlencoding::decode;
-use lazy_static::lazy_static;
-
-lazy_static! {
-    static ref URL_REGEX: Regex = Regex::new(r"/url\?q=([^&]*)&").unwrap();
-}
+use tonic::metadata::{MetadataMap, MetadataValue};
+use bytes::Bytes;
+use prost::Message;
+use serde::{Deserialize, Serialize};
 
-pub struct GetPlaceRequest<'a> {
-    pub client: &'a mut MapsJsInternalServiceClient<Channel>,
+pub struct GetPlaceRequest {
+    pub(crate) client: ServiceClient,
     pub location_id: String,
+    pub timeout: Option<Duration>,
+    pub language: String,
+    pub region: String,
+    pub(crate) archive: Option<PlaceArchive>,
 }
 
+/// Response of [`GetPlaceRequest::send_raw`].
+#[derive(Debug)]
+pub struct RawPlaceResponse {
+    pub response: GetEntityDetailsResponse,
+    /// The encoded response message as received, including fields unknown to `mapsjs.proto`.
+    pub bytes: Bytes,
+    pub metadata: MetadataMap,
+}
+
+/// A field required to build a [`Place`] was absent from the response.
+#[derive(Debug, Error)]
+#[error("Missing {0}")]
+pub struct MissingFieldError(pub &'static str);
+
 #[derive(Debug, Error)]
 pub enum GetPlaceError {
     #[error("Request error: {0}")]
     RequestError(#[from] RequestError),
+
+    /// Creating the client failed, see [`blocking::get_place`](crate::blocking::get_place).
+    #[error("Client error: {0}")]
+    Connect(#[source] MapsJsInternalServiceClientError),
 }
 
-use mapsjs::GetEntityDetailsRequest;
+use mapsjs::{GetEntityDetailsRequest, GetEntityDetailsResponse};
 use mapsjs::get_entity_details_request::{
     EntityQuery,
     entity_query::Entity,
     LocalizationContext
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Place {
     pub location_id: String,
     pub title: Option<String>,
@@ -46,62 +64,145 @@ pub struct Place {
     pub latitude: Option<i64>
 }
 
-impl<'a> GetPlaceRequest<'a> {
-    pub async fn send(self) -> Result<Place, GetPlaceError> {
-        let mut request = Request::new(GetEntityDetailsRequest {
+impl Place {
+    /// Returns `(latitude, longitude)` in degrees. The camera location is
+    /// reported in E7 units (degrees * 10^7).
+    pub fn lat_lng(&self) -> Option<(f64, f64)> {
+        Some((self.latitude? as f64 / 1e7, self.longitude? as f64 / 1e7))
+    }
+
+    /// Decodes `global_code` into the area it covers.
+    pub fn plus_code_area(&self) -> Option<pluscode::CodeArea> {
+        pluscode::decode(self.global_code.as_deref()?).ok()
+    }
+
+    /// Checks that the camera location lies within the plus code area. Returns
+    /// `None` if either is missing.
+    pub fn location_matches_plus_code(&self) -> Option<bool> {
+        let (lat, lng) = self.lat_lng()?;
+        Some(self.plus_code_area()?.contains(lat, lng))
+    }
+}
+
+impl GetPlaceRequest {
+    /// Overrides the client's request deadline for this lookup.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the language of the returned details, e.g. `de-DE`. Defaults to `en-US`.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    /// Sets the region the request is made from, e.g. `DE`. Defaults to `US`.
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Sends the request and returns the decoded response along with the bytes
+    /// it was decoded from and the response metadata.
+    pub async fn send_raw(mut self) -> Result<RawPlaceResponse, GetPlaceError> {
+        let message = GetEntityDetailsRequest {
             entity_query: Some(EntityQuery{
                 entity: Some(Entity{
                     feature_id: self.location_id.clone()
                 })
             }),
             localization_context: Some(LocalizationContext{
-                language: "en-US".to_string(),
-                region: "US".to_string()
+                language: self.language.clone(),
+                region: self.region.clone()
             }),
-        });
+        };
+        let mut request = Request::new(message.clone());
 
-        request.metadata_mut().insert("x-goog-fieldmask", MetadataValue::from_str("entityDetailsResult(title,localLanguageTitle,singleLineAddress,numRatingStars,phoneNumber,authorityPageLink.url,menuLink.url,category),camera.location").map_err(|e| RequestError::InvalidMetadata(e))?);
+        if let Some(timeout) = self.timeout {
+            request.set_timeout(timeout);
+        }
 
-        let response = self.client.get_entity_details(request).await
+        request.metadata_mut().insert("x-goog-fieldmask", MetadataValue::from_str("entityDetailsResult(title,localLanguageTitle,singleLineAddress,numRatingStars,phoneNumber,authorityPageLink.url,menuLink.url,category,plusCode),camera.location").map_err(RequestError::InvalidMetadata)?);
+
+        let (metadata, bytes, _) = self.client.get_entity_details(request).await
             .map_err(|e| match e.code() {
                 tonic::Code::ResourceExhausted => RequestError::RateLimited,
                 tonic::Code::NotFound => RequestError::NotFound,
                 _ => RequestError::TonicStatus(e),
             })?
-            .into_inner();
+            .into_parts();
+
+        // The archive is only kept for re-parsing later, so failing to write
+        // it doesn't fail the lookup. Failures are counted by the archive.
+        if let Some(archive) = &self.archive {
+            if let Err(e) = archive.write(&message, &bytes).await {
+                tracing::warn!(location_id = %self.location_id, "Failed to archive place response: {}", e);
+            }
+        }
+
+        let response = GetEntityDetailsResponse::decode(bytes.clone())
+            .map_err(|e| RequestError::Other(format!("Failed to decode response: {}", e)))?;
+
+        Ok(RawPlaceResponse {
+            response,
+            bytes,
+            metadata,
+        })
+    }
 
+    pub async fn send(self) -> Result<Place, GetPlaceError> {
+        let location_id = self.location_id.clone();
+        let raw = self.send_raw().await?;
+        Place::from_response(location_id, raw.response)
+            .map_err(|e| RequestError::Other(e.to_string()).into())
+    }
+}
+
+impl Place {
+    /// Builds a place from a decoded response, e.g. one read back from a [`PlaceArchive`].
+    pub fn from_response(location_id: String, response: GetEntityDetailsResponse) -> Result<Place, MissingFieldError> {
         let entity_details = response.entity_details_result
-            .ok_or_else(|| RequestError::Other("Missing entity details".to_string()))?;
-        let camera = response.camera
-            .ok_or_else(|| RequestError::Other("Missing camera information".to_string()))?;
-        let location = camera.location
-            .ok_or_else(|| RequestError::Other("Missing location information".to_string()))?;
+            .ok_or(MissingFieldError("entity details"))?;
+        let global_code = entity_details.plus_code.as_ref()
+            .and_then(|code| code.global_code.as_ref())
+            .map(|global| global.raw_text.clone());
+
+        // Without a camera location, fall back to the center of the plus code.
+        let (altitude, longitude, latitude) = match response.camera.and_then(|camera| camera.location) {
+            Some(location) => (
+                location.altitude.parse().ok(),
+                location.longitude.parse().ok(),
+                location.latitude.parse().ok(),
+            ),
+            None => {
+                let area = global_code.as_deref()
+                    .and_then(|code| pluscode::decode(code).ok())
+                    .ok_or(MissingFieldError("location information"))?;
+                let (lat, lng) = area.center();
+                (None, Some((lng * 1e7).round() as i64), Some((lat * 1e7).round() as i64))
+            }
+        };
 
         let extract_and_decode_url = |url: Option<String>| -> Option<String> {
-            url.and_then(|u| {
-                URL_REGEX.captures(&u).and_then(|cap| {
-                    cap.get(1).and_then(|m| decode(m.as_str()).ok().map(|s| s.into_owned()))
-                })
-            })
+            url.and_then(|u| url::unwrap_redirect(&u))
         };
 
         Ok(Place {
-            location_id: self.location_id,
+            location_id,
             title: Some(entity_details.title),
             local_language_title: Some(entity_details.local_language_title),
             rating: Some(entity_details.num_rating_stars),
             phone: Some(entity_details.phone_number),
             url: extract_and_decode_url(entity_details.authority_page_link.map(|link| link.url)),
             menu_url: extract_and_decode_url(entity_details.menu_link.map(|link| link.url)),
-            global_code: entity_details.plus_code.as_ref()
-                .and_then(|code| code.global_code.as_ref())
-                .map(|global| global.raw_text.clone()),
+            global_code,
             compound_code: entity_details.plus_code.as_ref()
                 .and_then(|code| code.compound_code.as_ref())
                 .map(|compound| compound.compound_code.clone()),
-            altitude: location.altitude.parse().ok(),
-            longitude: location.longitude.parse().ok(),
-            latitude: location.latitude.parse().ok(),
+            altitude,
+            longitude,
+            latitude,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,402 @@
+//! Plus Codes ([Open Location Code](https://github.com/google/open-location-code)),
+//! as returned in `Place::global_code` and `Place::compound_code`.
+
+use thiserror::Error;
+
+const SEPARATOR: char = '+';
+const SEPARATOR_POSITION: usize = 8;
+const PADDING: char = '0';
+const ALPHABET: &[u8; 20] = b"23456789CFGHJMPQRVWX";
+const ENCODING_BASE: i64 = 20;
+
+const LATITUDE_MAX: i64 = 90;
+const LONGITUDE_MAX: i64 = 180;
+
+const MIN_DIGIT_COUNT: usize = 2;
+const MAX_DIGIT_COUNT: usize = 15;
+const PAIR_CODE_LENGTH: usize = 10;
+const GRID_CODE_LENGTH: usize = MAX_DIGIT_COUNT - PAIR_CODE_LENGTH;
+const GRID_COLUMNS: i64 = 4;
+const GRID_ROWS: i64 = 5;
+const MIN_TRIMMABLE_CODE_LENGTH: usize = 6;
+
+// Value of the first pair digit and first grid digits.
+const PAIR_FIRST_PLACE_VALUE: i64 = 160_000; // 20^4
+const GRID_LAT_FIRST_PLACE_VALUE: i64 = 625; // 5^4
+const GRID_LNG_FIRST_PLACE_VALUE: i64 = 256; // 4^4
+
+// Integer multipliers for the pair and the full precision.
+const PAIR_PRECISION: i64 = 8_000; // 20^3
+const FINAL_LAT_PRECISION: i64 = PAIR_PRECISION * 3_125; // 5^5
+const FINAL_LNG_PRECISION: i64 = PAIR_PRECISION * 1_024; // 4^5
+
+// Size in degrees of each pair resolution, used to shorten codes.
+const PAIR_RESOLUTIONS: [f64; 5] = [20.0, 1.0, 0.05, 0.0025, 0.000125];
+
+/// Length of the codes returned in `global_code`, roughly 14x14 meters.
+pub const DEFAULT_CODE_LENGTH: usize = 10;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PlusCodeError {
+    #[error("Invalid plus code: {0}")]
+    InvalidCode(String),
+
+    #[error("Invalid code length: {0}")]
+    InvalidLength(usize),
+
+    #[error("Not a full plus code: {0}")]
+    NotFull(String),
+
+    #[error("Padded codes can't be shortened: {0}")]
+    Padded(String),
+}
+
+/// The area covered by a plus code, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CodeArea {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+    pub code_length: usize,
+}
+
+impl CodeArea {
+    /// Returns the `(latitude, longitude)` center of the area.
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.south + (self.north - self.south) / 2.0).min(LATITUDE_MAX as f64),
+            (self.west + (self.east - self.west) / 2.0).min(LONGITUDE_MAX as f64),
+        )
+    }
+
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        self.south <= latitude && latitude < self.north
+            && self.west <= longitude && longitude < self.east
+    }
+}
+
+/// Checks that `code` is a valid full or short plus code.
+pub fn is_valid(code: &str) -> bool {
+    let code = code.as_bytes();
+    let Some(separator) = code.iter().position(|&c| c == SEPARATOR as u8) else {
+        return false;
+    };
+    if code.iter().filter(|&&c| c == SEPARATOR as u8).count() != 1
+        || separator > SEPARATOR_POSITION
+        || separator % 2 == 1
+    {
+        return false;
+    }
+    // A single character after the separator is not allowed.
+    if code.len() - separator - 1 == 1 {
+        return false;
+    }
+    // Short codes like `+2VX` may have nothing before the separator, but a
+    // code needs digits somewhere.
+    if separator == 0 && code.len() == 1 {
+        return false;
+    }
+
+    if let Some(padding) = code.iter().position(|&c| c == PADDING as u8) {
+        // Padding is only allowed in full codes, in pairs, right before the separator.
+        let padding_len = code.iter().filter(|&&c| c == PADDING as u8).count();
+        if separator < SEPARATOR_POSITION
+            || padding == 0
+            || padding_len % 2 == 1
+            || padding + padding_len != separator
+            || separator != code.len() - 1
+        {
+            return false;
+        }
+    }
+
+    code.iter()
+        .filter(|&&c| c != SEPARATOR as u8 && c != PADDING as u8)
+        .all(|&c| digit_value(c).is_some())
+}
+
+/// A short code has had its first digits removed, see [`shorten`].
+pub fn is_short(code: &str) -> bool {
+    is_valid(code) && code.find(SEPARATOR).is_some_and(|i| i < SEPARATOR_POSITION)
+}
+
+pub fn is_full(code: &str) -> bool {
+    if !is_valid(code) || is_short(code) {
+        return false;
+    }
+    let code = code.as_bytes();
+    // The first pair must stay within the latitude and longitude range.
+    let first_lat = digit_value(code[0]).unwrap_or(0) * ENCODING_BASE;
+    let first_lng = code.get(1).and_then(|&c| digit_value(c)).unwrap_or(0) * ENCODING_BASE;
+    first_lat < 2 * LATITUDE_MAX && first_lng < 2 * LONGITUDE_MAX
+}
+
+/// Encodes a location into a full plus code of `code_length` digits.
+pub fn encode(latitude: f64, longitude: f64, code_length: usize) -> Result<String, PlusCodeError> {
+    let code_length = code_length.min(MAX_DIGIT_COUNT);
+    if code_length < MIN_DIGIT_COUNT || (code_length < PAIR_CODE_LENGTH && code_length % 2 == 1) {
+        return Err(PlusCodeError::InvalidLength(code_length));
+    }
+
+    let (mut lat_val, mut lng_val) = location_to_integers(latitude, longitude);
+    let mut reversed = Vec::with_capacity(MAX_DIGIT_COUNT + 1);
+
+    if code_length > PAIR_CODE_LENGTH {
+        for _ in 0..GRID_CODE_LENGTH {
+            let index = (lat_val % GRID_ROWS) * GRID_COLUMNS + lng_val % GRID_COLUMNS;
+            reversed.push(ALPHABET[index as usize]);
+            lat_val /= GRID_ROWS;
+            lng_val /= GRID_COLUMNS;
+        }
+    } else {
+        lat_val /= FINAL_LAT_PRECISION / PAIR_PRECISION;
+        lng_val /= FINAL_LNG_PRECISION / PAIR_PRECISION;
+    }
+
+    for _ in 0..PAIR_CODE_LENGTH / 2 {
+        reversed.push(ALPHABET[(lng_val % ENCODING_BASE) as usize]);
+        reversed.push(ALPHABET[(lat_val % ENCODING_BASE) as usize]);
+        lat_val /= ENCODING_BASE;
+        lng_val /= ENCODING_BASE;
+    }
+
+    let digits: String = reversed.iter().rev().map(|&c| c as char).collect();
+    let mut code = String::with_capacity(MAX_DIGIT_COUNT + 1);
+    if code_length >= SEPARATOR_POSITION {
+        code.push_str(&digits[..SEPARATOR_POSITION]);
+        code.push(SEPARATOR);
+        code.push_str(&digits[SEPARATOR_POSITION..code_length]);
+    } else {
+        code.push_str(&digits[..code_length]);
+        code.extend(std::iter::repeat_n(PADDING, SEPARATOR_POSITION - code_length));
+        code.push(SEPARATOR);
+    }
+    Ok(code)
+}
+
+/// Decodes a full plus code into the area it covers.
+pub fn decode(code: &str) -> Result<CodeArea, PlusCodeError> {
+    if !is_full(code) {
+        return Err(PlusCodeError::NotFull(code.to_string()));
+    }
+    let digits: Vec<i64> = code.bytes()
+        .filter(|&c| c != SEPARATOR as u8 && c != PADDING as u8)
+        .take(MAX_DIGIT_COUNT)
+        .filter_map(digit_value)
+        .collect();
+
+    let mut normal_lat = -LATITUDE_MAX * PAIR_PRECISION;
+    let mut normal_lng = -LONGITUDE_MAX * PAIR_PRECISION;
+    let pair_digits = digits.len().min(PAIR_CODE_LENGTH);
+    let mut place_value = PAIR_FIRST_PLACE_VALUE;
+    for i in (0..pair_digits).step_by(2) {
+        normal_lat += digits[i] * place_value;
+        normal_lng += digits[i + 1] * place_value;
+        if i + 2 < pair_digits {
+            place_value /= ENCODING_BASE;
+        }
+    }
+    let mut lat_precision = place_value as f64 / PAIR_PRECISION as f64;
+    let mut lng_precision = place_value as f64 / PAIR_PRECISION as f64;
+
+    let mut grid_lat = 0;
+    let mut grid_lng = 0;
+    if digits.len() > PAIR_CODE_LENGTH {
+        let mut row_value = GRID_LAT_FIRST_PLACE_VALUE;
+        let mut column_value = GRID_LNG_FIRST_PLACE_VALUE;
+        for i in PAIR_CODE_LENGTH..digits.len() {
+            grid_lat += (digits[i] / GRID_COLUMNS) * row_value;
+            grid_lng += (digits[i] % GRID_COLUMNS) * column_value;
+            if i + 1 < digits.len() {
+                row_value /= GRID_ROWS;
+                column_value /= GRID_COLUMNS;
+            }
+        }
+        lat_precision = row_value as f64 / FINAL_LAT_PRECISION as f64;
+        lng_precision = column_value as f64 / FINAL_LNG_PRECISION as f64;
+    }
+
+    let south = normal_lat as f64 / PAIR_PRECISION as f64 + grid_lat as f64 / FINAL_LAT_PRECISION as f64;
+    let west = normal_lng as f64 / PAIR_PRECISION as f64 + grid_lng as f64 / FINAL_LNG_PRECISION as f64;
+    Ok(CodeArea {
+        south,
+        west,
+        north: south + lat_precision,
+        east: west + lng_precision,
+        code_length: digits.len(),
+    })
+}
+
+/// Removes as many leading digits as possible while the code can still be
+/// recovered near the reference location.
+pub fn shorten(code: &str, latitude: f64, longitude: f64) -> Result<String, PlusCodeError> {
+    if !is_full(code) {
+        return Err(PlusCodeError::NotFull(code.to_string()));
+    }
+    if code.contains(PADDING) {
+        return Err(PlusCodeError::Padded(code.to_string()));
+    }
+    let code = code.to_ascii_uppercase();
+    let area = decode(&code)?;
+    if area.code_length < MIN_TRIMMABLE_CODE_LENGTH {
+        return Err(PlusCodeError::InvalidLength(area.code_length));
+    }
+
+    let (center_lat, center_lng) = area.center();
+    let range = (center_lat - clip_latitude(latitude)).abs()
+        .max((center_lng - normalize_longitude(longitude)).abs());
+
+    for i in (1..PAIR_RESOLUTIONS.len() - 1).rev() {
+        // Leave a safety margin so the code isn't ambiguous near cell edges.
+        if range < PAIR_RESOLUTIONS[i] * 0.3 {
+            return Ok(code[(i + 1) * 2..].to_string());
+        }
+    }
+    Ok(code)
+}
+
+/// Recovers the full code nearest to the reference location from a short
+/// code, e.g. the `Q257+9P` part of a compound code. Full codes are returned as is.
+pub fn recover_nearest(short_code: &str, latitude: f64, longitude: f64) -> Result<String, PlusCodeError> {
+    if !is_short(short_code) {
+        return match is_full(short_code) {
+            true => Ok(short_code.to_ascii_uppercase()),
+            false => Err(PlusCodeError::InvalidCode(short_code.to_string())),
+        };
+    }
+
+    let latitude = clip_latitude(latitude);
+    let longitude = normalize_longitude(longitude);
+    let short_code = short_code.to_ascii_uppercase();
+
+    let padding_length = SEPARATOR_POSITION - short_code.find(SEPARATOR).unwrap_or(0);
+    let resolution = 20f64.powi(2 - (padding_length / 2) as i32);
+    let half_resolution = resolution / 2.0;
+
+    let prefix = encode(latitude, longitude, DEFAULT_CODE_LENGTH)?;
+    let area = decode(&format!("{}{}", &prefix[..padding_length], short_code))?;
+    let (mut center_lat, mut center_lng) = area.center();
+
+    // The nearest match may be in the neighbouring cell of the reference.
+    if latitude + half_resolution < center_lat && center_lat - resolution >= -(LATITUDE_MAX as f64) {
+        center_lat -= resolution;
+    } else if latitude - half_resolution > center_lat && center_lat + resolution <= LATITUDE_MAX as f64 {
+        center_lat += resolution;
+    }
+    if longitude + half_resolution < center_lng {
+        center_lng -= resolution;
+    } else if longitude - half_resolution > center_lng {
+        center_lng += resolution;
+    }
+
+    encode(center_lat, center_lng, area.code_length)
+}
+
+fn digit_value(c: u8) -> Option<i64> {
+    ALPHABET.iter()
+        .position(|&a| a == c.to_ascii_uppercase())
+        .map(|i| i as i64)
+}
+
+fn clip_latitude(latitude: f64) -> f64 {
+    latitude.clamp(-(LATITUDE_MAX as f64), LATITUDE_MAX as f64)
+}
+
+fn normalize_longitude(longitude: f64) -> f64 {
+    let mut longitude = longitude;
+    while longitude < -(LONGITUDE_MAX as f64) {
+        longitude += 360.0;
+    }
+    while longitude >= LONGITUDE_MAX as f64 {
+        longitude -= 360.0;
+    }
+    longitude
+}
+
+fn location_to_integers(latitude: f64, longitude: f64) -> (i64, i64) {
+    // Rounding first avoids floating point errors right at cell boundaries.
+    let mut lat_val = ((latitude * FINAL_LAT_PRECISION as f64 * 1e6).round() / 1e6).floor() as i64;
+    lat_val += LATITUDE_MAX * FINAL_LAT_PRECISION;
+    lat_val = lat_val.clamp(0, 2 * LATITUDE_MAX * FINAL_LAT_PRECISION - 1);
+
+    let mut lng_val = ((longitude * FINAL_LNG_PRECISION as f64 * 1e6).round() / 1e6).floor() as i64;
+    lng_val += LONGITUDE_MAX * FINAL_LNG_PRECISION;
+    lng_val = lng_val.rem_euclid(2 * LONGITUDE_MAX * FINAL_LNG_PRECISION);
+
+    (lat_val, lng_val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(20.375, 2.775, 6).unwrap(), "7FG49Q00+");
+        assert_eq!(encode(20.3700625, 2.7821875, 10).unwrap(), "7FG49QCJ+2V");
+        assert_eq!(encode(20.3701125, 2.782234375, 11).unwrap(), "7FG49QCJ+2VX");
+        assert_eq!(encode(47.0000625, 8.0000625, 10).unwrap(), "8FVC2222+22");
+        assert_eq!(encode(-41.2730625, 174.7859375, 10).unwrap(), "4VCPPQGP+Q9");
+        assert_eq!(encode(0.5, -179.5, 4).unwrap(), "62G20000+");
+        assert_eq!(encode(-89.5, -179.5, 4).unwrap(), "22220000+");
+        assert_eq!(encode(90.0, 1.0, 10).unwrap(), "CFX3X2X2+X2");
+        assert_eq!(encode(1.0, 180.0, 4).unwrap(), encode(1.0, -180.0, 4).unwrap());
+        assert_eq!(encode(1.0, 1.0, 7), Err(PlusCodeError::InvalidLength(7)));
+    }
+
+    #[test]
+    fn test_decode() {
+        let area = decode("7FG49QCJ+2V").unwrap();
+        assert!((area.south - 20.37).abs() < 1e-9);
+        assert!((area.west - 2.782125).abs() < 1e-9);
+        assert!((area.north - 20.370125).abs() < 1e-9);
+        assert!((area.east - 2.78225).abs() < 1e-9);
+        assert_eq!(area.code_length, 10);
+
+        let area = decode("7FG49Q00+").unwrap();
+        assert_eq!(area.code_length, 6);
+        assert!(area.contains(20.375, 2.775));
+
+        for code in ["8FVC9G8F+6X", "4VCPPQGP+Q9", "7FG49QCJ+2VXGJ"] {
+            let area = decode(code).unwrap();
+            let (lat, lng) = area.center();
+            assert_eq!(encode(lat, lng, area.code_length).unwrap(), code);
+        }
+
+        assert!(decode("QCJ+2V").is_err());
+        assert!(decode("7FG49QCJ+2").is_err());
+        assert!(decode("7FG4900+").is_err());
+    }
+
+    #[test]
+    fn test_is_valid() {
+        for code in ["7FG49QCJ+2V", "7FG49Q00+", "CJ+2VX", "+2VX"] {
+            assert!(is_valid(code), "{}", code);
+        }
+        for code in ["+", "", "7FG49QCJ", "7FG49QCJ+2", "7FG+49QCJ", "7FG49QCJ0+", "7FG4900+", "7FG4++2V"] {
+            assert!(!is_valid(code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_shorten_and_recover() {
+        let code = "9C3W9QCJ+2VX";
+        let cases = [
+            (51.3701125, -1.217765625, "+2VX"),
+            (51.3708675, -1.217765625, "CJ+2VX"),
+            (51.3693575, -1.217765625, "CJ+2VX"),
+            (51.3701125, -1.218520625, "CJ+2VX"),
+            (51.3701125, -1.217010625, "CJ+2VX"),
+        ];
+        for (lat, lng, short) in cases {
+            assert_eq!(shorten(code, lat, lng).unwrap(), short);
+            assert_eq!(recover_nearest(short, lat, lng).unwrap(), code);
+        }
+
+        // The nearest match can be across the reference cell boundary.
+        assert_eq!(recover_nearest("X2X2+X2", 89.6, 0.9).unwrap(), "CFX3X2X2+X2");
+        assert_eq!(recover_nearest("2222+22", 0.0, 179.99).unwrap(), "62G22222+22");
+        assert!(shorten("7FG40000+", 20.5, 2.5).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+use crate::tiles::types::{Tile, TileFeature};
+
+const SEGMENT_PREFIX: &str = "tiles-";
+const SEGMENT_SUFFIX: &str = ".seg";
+const COMPRESSION_LEVEL: i32 = 3;
+const DEFAULT_SEGMENT_SIZE: u64 = 256 << 20;
+
+/// Append-only archive of raw `/maps/vt` responses and the tiles they were
+/// requested for, so tiles can be re-parsed without re-crawling them.
+///
+/// Records are zstd-compressed one at a time and written to numbered segment
+/// files in a directory. A new segment is started when the current one passes
+/// the segment size and whenever the archive is opened, so existing segments
+/// are never modified. Call [`flush`](Self::flush) before dropping the last handle.
+#[derive(Clone)]
+pub struct TileArchive {
+    dir: Arc<PathBuf>,
+    segment_size: u64,
+    writer: Arc<Mutex<SegmentWriter>>,
+}
+
+struct SegmentWriter {
+    index: u64,
+    written: u64,
+    file: BufWriter<tokio::fs::File>,
+}
+
+impl TileArchive {
+    /// Opens the archive in `dir`, creating it if needed, with 256 MiB segments.
+    pub async fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::with_segment_size(dir, DEFAULT_SEGMENT_SIZE).await
+    }
+
+    pub async fn with_segment_size(dir: impl AsRef<Path>, segment_size: u64) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let index = segments(&dir)?.last()
+            .map(|(index, _)| index + 1)
+            .unwrap_or(0);
+        let file = create_segment(&dir, index).await?;
+
+        Ok(Self {
+            dir: Arc::new(dir),
+            segment_size,
+            writer: Arc::new(Mutex::new(SegmentWriter { index, written: 0, file })),
+        })
+    }
+
+    pub async fn write(&self, tiles: &[Tile], body: &[u8]) -> io::Result<()> {
+        let mut payload = BytesMut::with_capacity(body.len() + tiles.len() * 12 + 4);
+        prost::encoding::encode_varint(tiles.len() as u64, &mut payload);
+        for tile in tiles {
+            prost::encoding::encode_varint(tile.zoom as u64, &mut payload);
+            prost::encoding::encode_varint(tile.x as u64, &mut payload);
+            prost::encoding::encode_varint(tile.y as u64, &mut payload);
+        }
+        payload.extend_from_slice(body);
+
+        let compressed = zstd::bulk::compress(&payload, COMPRESSION_LEVEL)?;
+        let mut record = BytesMut::with_capacity(compressed.len() + 10);
+        prost::encoding::encode_varint(compressed.len() as u64, &mut record);
+        record.extend_from_slice(&compressed);
+
+        let mut writer = self.writer.lock().await;
+        if writer.written > 0 && writer.written + record.len() as u64 > self.segment_size {
+            writer.file.flush().await?;
+            writer.index += 1;
+            writer.written = 0;
+            writer.file = create_segment(&self.dir, writer.index).await?;
+        }
+        // One write per record keeps records whole when the archive is shared.
+        writer.file.write_all(&record).await?;
+        writer.written += record.len() as u64;
+        Ok(())
+    }
+
+    pub async fn flush(&self) -> io::Result<()> {
+        self.writer.lock().await.file.flush().await
+    }
+}
+
+async fn create_segment(dir: &Path, index: u64) -> io::Result<BufWriter<tokio::fs::File>> {
+    let path = dir.join(format!("{}{:08}{}", SEGMENT_PREFIX, index, SEGMENT_SUFFIX));
+    let file = tokio::fs::OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .open(path)
+        .await?;
+    Ok(BufWriter::new(file))
+}
+
+/// Returns the segments in `dir` ordered by index.
+fn segments(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let index = path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(SEGMENT_PREFIX))
+            .and_then(|name| name.strip_suffix(SEGMENT_SUFFIX))
+            .and_then(|index| index.parse::<u64>().ok());
+        if let Some(index) = index {
+            segments.push((index, path));
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// A single archived response.
+#[derive(Debug, Clone)]
+pub struct TileArchiveRecord {
+    pub tiles: Vec<Tile>,
+    pub body: Bytes,
+}
+
+impl TileArchiveRecord {
+    /// Parses the response with the current tile parser.
+    pub fn features(&self) -> Result<Vec<TileFeature>, crate::tiles::error::FetchTilesError> {
+        crate::tiles::parse_features(&self.body, &self.tiles)
+    }
+
+    fn decode(compressed: &[u8]) -> io::Result<Self> {
+        let payload = zstd::stream::decode_all(compressed)?;
+        let mut payload = Bytes::from(payload);
+        let invalid = |e: prost::DecodeError| io::Error::new(io::ErrorKind::InvalidData, e);
+
+        let count = prost::encoding::decode_varint(&mut payload).map_err(invalid)?;
+        let mut tiles = Vec::with_capacity(count.min(1024) as usize);
+        for _ in 0..count {
+            let zoom = prost::encoding::decode_varint(&mut payload).map_err(invalid)?;
+            let x = prost::encoding::decode_varint(&mut payload).map_err(invalid)?;
+            let y = prost::encoding::decode_varint(&mut payload).map_err(invalid)?;
+            // Anything else would overflow when converted to coordinates
+            if zoom >= 32 || x >= 1 << zoom || y >= 1 << zoom {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid tile {}/{}/{} in tile archive record", zoom, x, y)));
+            }
+            tiles.push(Tile { x: x as usize, y: y as usize, zoom: zoom as usize });
+        }
+
+        Ok(Self { tiles, body: payload })
+    }
+}
+
+/// Reads every record of a [`TileArchive`] directory, segment by segment.
+///
+/// A damaged record, e.g. a truncated one at the end of a segment that was
+/// being written during a crash, is reported as an error and the reader moves
+/// on to the next segment.
+pub struct TileArchiveReader {
+    segments: std::vec::IntoIter<(u64, PathBuf)>,
+    current: Option<BufReader<File>>,
+}
+
+impl TileArchiveReader {
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            segments: segments(dir.as_ref())?.into_iter(),
+            current: None,
+        })
+    }
+
+    /// Reads the next compressed record from the current segment, or `None`
+    /// at its end.
+    fn next_record(reader: &mut BufReader<File>) -> io::Result<Option<Vec<u8>>> {
+        let mut len = 0u64;
+        for (i, shift) in (0..64).step_by(7).enumerate() {
+            let mut byte = [0u8];
+            if reader.read(&mut byte)? == 0 {
+                return match i {
+                    0 => Ok(None),
+                    _ => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated tile archive record")),
+                };
+            }
+            len |= ((byte[0] & 0x7f) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                let mut record = vec![0; len as usize];
+                reader.read_exact(&mut record)?;
+                return Ok(Some(record));
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "invalid tile archive record length"))
+    }
+}
+
+impl Iterator for TileArchiveReader {
+    type Item = io::Result<TileArchiveRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reader = match &mut self.current {
+                Some(reader) => reader,
+                None => {
+                    let (_, path) = self.segments.next()?;
+                    match File::open(path) {
+                        Ok(file) => self.current.insert(BufReader::new(file)),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            };
+
+            match Self::next_record(reader) {
+                Ok(Some(record)) => return Some(TileArchiveRecord::decode(&record)),
+                Ok(None) => self.current = None,
+                Err(e) => {
+                    self.current = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("maps-tile-archive-{}", std::process::id()));
+        let tiles = vec![Tile { x: 19299, y: 24631, zoom: 16 }, Tile { x: 19300, y: 24631, zoom: 16 }];
+        let body = br#"[{"features":[{"id":"15074921902713971043"}]},{"features":[]}]"#;
+
+        let archive = TileArchive::with_segment_size(&dir, 64).await.unwrap();
+        for _ in 0..3 {
+            archive.write(&tiles, body).await.unwrap();
+        }
+        archive.flush().await.unwrap();
+
+        // Reopening starts a new segment instead of appending to old ones
+        let archive = TileArchive::open(&dir).await.unwrap();
+        archive.write(&tiles[..1], b"[]").await.unwrap();
+        archive.flush().await.unwrap();
+        assert_eq!(segments(&dir).unwrap().len(), 4);
+
+        let records: Vec<TileArchiveRecord> = TileArchiveReader::open(&dir).unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].tiles, tiles);
+        assert_eq!(&records[0].body[..], body);
+        assert_eq!(records[0].features().unwrap()[0].id, "d134e199a405a163");
+        assert_eq!(records[3].tiles, tiles[..1]);
+    }
+
+    #[test]
+    fn test_decode_invalid_zoom() {
+        let mut payload = BytesMut::new();
+        for value in [1, 40, 0, 0] {
+            prost::encoding::encode_varint(value, &mut payload);
+        }
+        let compressed = zstd::bulk::compress(&payload, COMPRESSION_LEVEL).unwrap();
+        let error = TileArchiveRecord::decode(&compressed).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
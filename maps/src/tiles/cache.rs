@@ -0,0 +1,222 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bytes::Bytes;
+use redb::{Database, Durability, ReadableTable, ReadableTableMetadata, TableDefinition};
+use thiserror::Error;
+
+/// Tile response per cache key, prefixed with the insertion time in seconds
+/// and the insertion sequence number.
+const TILES: TableDefinition<&str, &[u8]> = TableDefinition::new("tiles");
+/// Response size by insertion sequence number and cache key, for evicting
+/// the oldest entries first.
+const BY_AGE: TableDefinition<(u64, &str), u64> = TableDefinition::new("tiles_by_age");
+const META: TableDefinition<&str, u64> = TableDefinition::new("meta");
+
+const TOTAL_BYTES: &str = "total_bytes";
+const NEXT_SEQUENCE: &str = "next_sequence";
+const HEADER_LEN: usize = 16;
+const DEFAULT_MAX_BYTES: u64 = 1 << 30;
+
+/// Any error from the underlying database, boxed since `redb::Error` is large.
+#[derive(Error, Debug)]
+#[error("{0}")]
+pub struct TileCacheError(Box<redb::Error>);
+
+impl<E: Into<redb::Error>> From<E> for TileCacheError {
+    fn from(e: E) -> Self {
+        Self(Box::new(e.into()))
+    }
+}
+
+/// On-disk cache of tile responses for [`TilesClient`](crate::tiles::TilesClient).
+///
+/// Entries are keyed by tile, language, region and map version. Entries older
+/// than the TTL count as misses, and once the cache grows past its size cap
+/// the oldest entries are evicted. In `cache_only` mode nothing is fetched:
+/// every cached entry is used regardless of age and missed tiles are counted
+/// in [`ParseReport::not_cached`](crate::tiles::ParseReport::not_cached).
+#[derive(Clone)]
+pub struct TileCache {
+    db: Arc<Database>,
+    ttl: Duration,
+    max_bytes: u64,
+    cache_only: bool,
+}
+
+impl TileCache {
+    /// Opens or creates the cache at `path`. The size cap defaults to 1 GiB.
+    pub fn open(path: impl AsRef<Path>, ttl: Duration) -> Result<Self, TileCacheError> {
+        let db = Database::create(path)?;
+
+        let txn = db.begin_write()?;
+        txn.open_table(TILES)?;
+        txn.open_table(BY_AGE)?;
+        txn.open_table(META)?;
+        txn.commit()?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            ttl,
+            max_bytes: DEFAULT_MAX_BYTES,
+            cache_only: false,
+        })
+    }
+
+    /// Caps the total size of the cached responses.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn cache_only(mut self, cache_only: bool) -> Self {
+        self.cache_only = cache_only;
+        self
+    }
+
+    pub fn is_cache_only(&self) -> bool {
+        self.cache_only
+    }
+
+    pub fn len(&self) -> Result<u64, TileCacheError> {
+        Ok(self.db.begin_read()?.open_table(TILES)?.len()?)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, TileCacheError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Total size of the cached responses in bytes.
+    pub fn size(&self) -> Result<u64, TileCacheError> {
+        let txn = self.db.begin_read()?;
+        let meta = txn.open_table(META)?;
+        Ok(meta.get(TOTAL_BYTES)?.map(|total| total.value()).unwrap_or(0))
+    }
+
+    /// Looks up every key, returning `None` for misses and expired entries.
+    pub(crate) async fn get_many(&self, keys: Vec<String>) -> Result<Vec<Option<Bytes>>, TileCacheError> {
+        let cache = self.clone();
+        run_blocking(move || {
+            let txn = cache.db.begin_read()?;
+            let table = txn.open_table(TILES)?;
+            let now = now_secs();
+
+            keys.iter()
+                .map(|key| {
+                    let Some(entry) = table.get(key.as_str())? else {
+                        return Ok(None);
+                    };
+                    let (inserted_at, _, body) = split_entry(entry.value());
+                    let fresh = cache.cache_only || now.saturating_sub(inserted_at) < cache.ttl.as_secs();
+                    Ok(fresh.then(|| Bytes::copy_from_slice(body)))
+                })
+                .collect()
+        }).await
+    }
+
+    /// Stores every entry, replacing older versions and evicting the oldest
+    /// entries if the cache grows past its size cap.
+    pub(crate) async fn insert_many(&self, entries: Vec<(String, Bytes)>) -> Result<(), TileCacheError> {
+        let cache = self.clone();
+        run_blocking(move || {
+            let mut txn = cache.db.begin_write()?;
+            // Losing the last few entries in a crash only costs a re-download.
+            txn.set_durability(Durability::Eventual);
+            {
+                let mut tiles = txn.open_table(TILES)?;
+                let mut by_age = txn.open_table(BY_AGE)?;
+                let mut meta = txn.open_table(META)?;
+                let mut total = meta.get(TOTAL_BYTES)?.map(|total| total.value()).unwrap_or(0);
+                let mut sequence = meta.get(NEXT_SEQUENCE)?.map(|sequence| sequence.value()).unwrap_or(0);
+                let now = now_secs();
+
+                for (key, body) in entries {
+                    let mut value = Vec::with_capacity(HEADER_LEN + body.len());
+                    value.extend_from_slice(&now.to_be_bytes());
+                    value.extend_from_slice(&sequence.to_be_bytes());
+                    value.extend_from_slice(&body);
+
+                    if let Some(old) = tiles.insert(key.as_str(), value.as_slice())? {
+                        let (_, old_sequence, old_body) = split_entry(old.value());
+                        total = total.saturating_sub(old_body.len() as u64);
+                        by_age.remove((old_sequence, key.as_str()))?;
+                    }
+                    by_age.insert((sequence, key.as_str()), body.len() as u64)?;
+                    total += body.len() as u64;
+                    sequence += 1;
+                }
+
+                while total > cache.max_bytes {
+                    let Some((entry, size)) = by_age.pop_first()? else {
+                        break;
+                    };
+                    let (_, key) = entry.value();
+                    tiles.remove(key)?;
+                    total = total.saturating_sub(size.value());
+                }
+
+                meta.insert(TOTAL_BYTES, total)?;
+                meta.insert(NEXT_SEQUENCE, sequence)?;
+            }
+            txn.commit()?;
+            Ok(())
+        }).await
+    }
+}
+
+/// Cache key for a tile requested with the given parameters.
+pub(crate) fn cache_key(tile: &crate::tiles::types::Tile, language: &str, region: &str, map_version: &str) -> String {
+    format!("{}/{}/{}|{}|{}|{}", tile.zoom, tile.x, tile.y, language, region, map_version)
+}
+
+/// Splits a stored value into insertion time, sequence number and response.
+fn split_entry(value: &[u8]) -> (u64, u64, &[u8]) {
+    let (header, body) = value.split_at(HEADER_LEN.min(value.len()));
+    let read = |range: std::ops::Range<usize>| header.get(range)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+        .unwrap_or(0);
+    (read(0..8), read(8..16), body)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+async fn run_blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T, TileCacheError> + Send + 'static) -> Result<T, TileCacheError> {
+    tokio::task::spawn_blocking(f).await
+        .map_err(|e| redb::Error::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cache() {
+        let path = std::env::temp_dir().join(format!("maps-tile-cache-{}.redb", std::process::id()));
+        let cache = TileCache::open(&path, Duration::from_secs(3600)).unwrap().max_bytes(10);
+
+        cache.insert_many(vec![("a".to_string(), Bytes::from("12345"))]).await.unwrap();
+        cache.insert_many(vec![("b".to_string(), Bytes::from("1234"))]).await.unwrap();
+        cache.insert_many(vec![("a".to_string(), Bytes::from("123"))]).await.unwrap();
+        assert_eq!(cache.size().unwrap(), 7);
+
+        // Over the cap, so the oldest entry ("b") is evicted
+        cache.insert_many(vec![("c".to_string(), Bytes::from("1234"))]).await.unwrap();
+        let entries = cache.get_many(vec!["a".to_string(), "b".to_string(), "c".to_string()]).await.unwrap();
+        assert_eq!(entries, vec![Some(Bytes::from("123")), None, Some(Bytes::from("1234"))]);
+        assert_eq!(cache.len().unwrap(), 2);
+
+        let expired = TileCache { ttl: Duration::ZERO, ..cache };
+        assert_eq!(expired.get_many(vec!["a".to_string()]).await.unwrap(), vec![None]);
+        let offline = expired.cache_only(true);
+        assert_eq!(offline.get_many(vec!["a".to_string()]).await.unwrap(), vec![Some(Bytes::from("123"))]);
+
+        drop(offline);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::{self, Read};
+use bytes::Bytes;
+
+/// Encodings requested from the tiles API, in order of preference.
+pub(crate) const ACCEPT_ENCODING: &str = "gzip, br";
+
+/// Largest decompressed body accepted, far above any real tile response.
+const MAX_DECODED_SIZE: u64 = 256 << 20;
+
+/// Decompresses a response body according to its `Content-Encoding`.
+pub(crate) fn decode_body(content_encoding: Option<&str>, body: Bytes) -> io::Result<Bytes> {
+    decode_body_limited(content_encoding, body, MAX_DECODED_SIZE)
+}
+
+fn decode_body_limited(content_encoding: Option<&str>, body: Bytes, limit: u64) -> io::Result<Bytes> {
+    let reader: Box<dyn Read + '_> = match content_encoding.map(str::trim) {
+        None | Some("") | Some("identity") => return Ok(body),
+        Some("gzip") => Box::new(flate2::read::MultiGzDecoder::new(&body[..])),
+        Some("br") => Box::new(brotli_decompressor::Decompressor::new(&body[..], 4096)),
+        Some(encoding) => return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported content encoding `{}`", encoding),
+        )),
+    };
+
+    // One byte over the limit tells a body at the limit from a larger one.
+    let mut decoded = Vec::with_capacity((body.len() * 4).min(limit as usize));
+    reader.take(limit + 1).read_to_end(&mut decoded)?;
+    if decoded.len() as u64 > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("decompressed body is larger than {} bytes", limit),
+        ));
+    }
+    Ok(decoded.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_decode_body() {
+        let body = Bytes::from_static(b"[{\"features\":[]}]");
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&body).unwrap();
+        let gzip = Bytes::from(gzip.finish().unwrap());
+
+        assert_eq!(decode_body(None, body.clone()).unwrap(), body);
+        assert_eq!(decode_body(Some("gzip"), gzip.clone()).unwrap(), body);
+        assert!(decode_body(Some("gzip"), body.clone()).is_err());
+        assert!(decode_body(Some("zstd"), gzip.clone()).is_err());
+
+        let mut br = Vec::new();
+        let mut writer = brotli::CompressorWriter::new(&mut br, 4096, 5, 22);
+        writer.write_all(&body).unwrap();
+        drop(writer);
+        assert_eq!(decode_body(Some("br"), Bytes::from(br)).unwrap(), body);
+
+        assert_eq!(decode_body_limited(Some("gzip"), gzip.clone(), body.len() as u64).unwrap(), body);
+        assert!(decode_body_limited(Some("gzip"), gzip, body.len() as u64 - 1).is_err());
+    }
+}
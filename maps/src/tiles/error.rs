@@ -22,6 +22,9 @@ pub enum FetchTilesError {
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
 
+    #[error("Unexpected response shape: {0}")]
+    UnexpectedShape(crate::tiles::parse::ParseReport),
+
     #[error("UTF-8 conversion error: {0}")]
     Utf8Error(#[from] FromUtf8Error),
 
@@ -29,5 +32,37 @@ pub enum FetchTilesError {
     HyperClientError(#[from] legacy::Error),
 
     #[error("Unexpected status code: {0}")]
-    UnexpectedStatusCode(u16)
+    UnexpectedStatusCode(u16),
+
+    /// The tile request was rejected and a newer map version was found, which
+    /// the client and its clones now use. Retrying should succeed.
+    #[error("Map version {0} was retired")]
+    StaleMapVersion(String),
+
+    #[error("No map version found in {0}")]
+    MapVersionNotFound(String),
+
+    #[error("Map version discovery failed recently, not retrying yet: {0}")]
+    MapVersionRefreshFailed(String),
+
+    #[error("Map version cache error: {0}")]
+    MapVersionCacheError(std::io::Error),
+
+    #[error("Failed to decompress response: {0}")]
+    DecompressionError(std::io::Error),
+
+    #[error("Cassette error: {0}")]
+    CassetteError(#[from] std::io::Error),
+
+    #[error("No recorded response for {0}")]
+    NotRecorded(String),
+
+    #[error("Cache error: {0}")]
+    CacheError(#[from] crate::tiles::cache::TileCacheError),
+
+    #[error("Parse task failed: {0}")]
+    ParseTaskError(#[from] tokio::task::JoinError),
+
+    #[error("Archive error: {0}")]
+    ArchiveError(std::io::Error),
 }
\ No newline at end of file
@@ -1,8 +1,19 @@
 mod error;
+#[allow(clippy::module_inception)]
 mod tiles;
 mod format;
 mod types;
+mod cache;
+mod archive;
+mod parse;
+mod encoding;
+mod version;
 
 pub use self::error::*;
 pub use self::tiles::*;
-pub use self::types::*;
\ No newline at end of file
+pub use self::types::*;
+pub use self::cache::{TileCache, TileCacheError};
+pub use self::archive::{TileArchive, TileArchiveReader, TileArchiveRecord};
+pub use self::format::DEFAULT_MAP_VERSION;
+pub use self::version::{parse_map_version, MapVersionCache, MAP_VERSION_BOOTSTRAP_PATH};
+pub use self::parse::{parse_features, parse_ids, parse_ids_with_report, parse_positions_with_report, ParseReport};
\ No newline at end of file
@@ -0,0 +1,426 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::AddAssign;
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::Value;
+use crate::tiles::error::FetchTilesError;
+use crate::tiles::types::{Tile, TileFeature};
+
+lazy_static! {
+    // Labels are usually in a JSON-like string that isn't always valid JSON.
+    static ref TITLE_REGEX: Regex = Regex::new(r#"title\\*"?\s*:\s*\\*"((?:[^"\\]|\\.)*)"#).unwrap();
+}
+
+/// What the parser had to skip in a response. Anything but zeros suggests the
+/// response format changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ParseReport {
+    /// Features without an ID that parses as a decimal `u64`.
+    pub skipped_features: usize,
+    /// Responses that aren't a JSON array of tiles.
+    pub non_array_roots: usize,
+    /// Tiles that aren't an object with a `features` array.
+    pub unknown_shapes: usize,
+    /// Tiles left out because a cache-only [`TileCache`](crate::tiles::TileCache)
+    /// didn't have them. Not a format problem, so not part of [`total`](Self::total).
+    pub not_cached: usize,
+}
+
+impl ParseReport {
+    pub fn is_clean(&self) -> bool {
+        self.total() == 0
+    }
+
+    pub fn total(&self) -> usize {
+        self.skipped_features + self.non_array_roots + self.unknown_shapes
+    }
+}
+
+impl AddAssign for ParseReport {
+    fn add_assign(&mut self, other: Self) {
+        self.skipped_features += other.skipped_features;
+        self.non_array_roots += other.non_array_roots;
+        self.unknown_shapes += other.unknown_shapes;
+        self.not_cached += other.not_cached;
+    }
+}
+
+impl fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} skipped features, {} non-array roots, {} unknown shapes",
+            self.skipped_features, self.non_array_roots, self.unknown_shapes
+        )?;
+        if self.not_cached > 0 {
+            write!(f, ", {} tiles not cached", self.not_cached)?;
+        }
+        Ok(())
+    }
+}
+
+/// Features are anchored in pixels of a tile this wide and high.
+const TILE_SIZE: f64 = 256.0;
+
+/// A tile in a `/maps/vt` response. Fields other than `id` and `features`
+/// are skipped without being parsed.
+#[derive(Deserialize)]
+struct Item<'a, F> {
+    #[serde(borrow, default)]
+    id: Option<&'a RawValue>,
+    features: Option<Vec<F>>,
+}
+
+impl<F> Item<'_, F> {
+    /// The tile named by the item's `zoom/x/y` ID, if it has one.
+    fn tile(&self) -> Option<Tile> {
+        serde_json::from_str::<&str>(self.id?.get()).ok()?.parse().ok()
+    }
+}
+
+/// A feature read for its ID only, skipping the label entirely.
+#[derive(Deserialize)]
+struct FeatureId<'a> {
+    #[serde(borrow, default)]
+    id: Option<&'a str>,
+}
+
+/// A feature read for its ID and anchor.
+#[derive(Deserialize)]
+struct PositionedFeature<'a> {
+    #[serde(borrow, default)]
+    id: Option<&'a str>,
+    #[serde(borrow, default)]
+    a: Option<&'a RawValue>,
+}
+
+/// A feature read for its ID, anchor and label content.
+#[derive(Deserialize)]
+struct LabeledFeature<'a> {
+    #[serde(borrow, default)]
+    id: Option<&'a str>,
+    #[serde(borrow, default)]
+    a: Option<&'a RawValue>,
+    #[serde(borrow, default)]
+    c: Option<Content<'a>>,
+}
+
+/// Label content, borrowed from the body unless it contains escapes.
+#[derive(Deserialize)]
+struct Content<'a>(#[serde(borrow)] Cow<'a, str>);
+
+trait RawFeature<'a>: Deserialize<'a> {
+    fn into_feature(self, tile: Option<&Tile>) -> Option<TileFeature>;
+}
+
+impl<'a> RawFeature<'a> for FeatureId<'a> {
+    fn into_feature(self, tile: Option<&Tile>) -> Option<TileFeature> {
+        Some(TileFeature {
+            id: hex_id(self.id?)?,
+            label: None,
+            tile: tile.cloned(),
+            lat_lng: None,
+        })
+    }
+}
+
+impl<'a> RawFeature<'a> for PositionedFeature<'a> {
+    fn into_feature(self, tile: Option<&Tile>) -> Option<TileFeature> {
+        Some(TileFeature {
+            id: hex_id(self.id?)?,
+            label: None,
+            tile: tile.cloned(),
+            lat_lng: anchor_lat_lng(self.a, tile),
+        })
+    }
+}
+
+impl<'a> RawFeature<'a> for LabeledFeature<'a> {
+    fn into_feature(self, tile: Option<&Tile>) -> Option<TileFeature> {
+        Some(TileFeature {
+            id: hex_id(self.id?)?,
+            label: self.c.and_then(|content| feature_label(&content.0)),
+            tile: tile.cloned(),
+            lat_lng: anchor_lat_lng(self.a, tile),
+        })
+    }
+}
+
+/// Responses carry IDs in decimal; everything else uses hex.
+fn hex_id(id: &str) -> Option<String> {
+    id.parse::<u64>().ok().map(|id| format!("{:x}", id))
+}
+
+/// Converts an `[x, y]` pixel anchor within `tile` to `(latitude, longitude)`.
+fn anchor_lat_lng(anchor: Option<&RawValue>, tile: Option<&Tile>) -> Option<(f64, f64)> {
+    match serde_json::from_str::<Vec<f64>>(anchor?.get()).ok()?[..] {
+        [x, y, ..] => Some(tile?.to_lat_lng(x / TILE_SIZE, y / TILE_SIZE)),
+        _ => None,
+    }
+}
+
+/// How much of each feature to parse. Less is faster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Detail {
+    Ids,
+    Positions,
+    Labels,
+}
+
+/// Parses a `/maps/vt` response body into its features.
+///
+/// `tiles` are the tiles the response was requested for, in order. They place
+/// features on the map when the response doesn't name its tiles, and may be
+/// empty.
+pub fn parse_features(body: &[u8], tiles: &[Tile]) -> Result<Vec<TileFeature>, FetchTilesError> {
+    Ok(parse_body::<LabeledFeature>(body, tiles)?.0)
+}
+
+/// Like [`parse_features`], but only parses the IDs, which is cheaper.
+pub fn parse_ids(body: &[u8]) -> Result<Vec<String>, FetchTilesError> {
+    Ok(parse_ids_with_report(body)?.0)
+}
+
+/// Like [`parse_ids`], but also reports what had to be skipped.
+pub fn parse_ids_with_report(body: &[u8]) -> Result<(Vec<String>, ParseReport), FetchTilesError> {
+    let (features, report) = parse_body::<FeatureId>(body, &[])?;
+    Ok((features.into_iter().map(|feature| feature.id).collect(), report))
+}
+
+/// Like [`parse_features`], but skips the labels and reports what had to be
+/// skipped.
+pub fn parse_positions_with_report(body: &[u8], tiles: &[Tile]) -> Result<(Vec<TileFeature>, ParseReport), FetchTilesError> {
+    parse_body::<PositionedFeature>(body, tiles)
+}
+
+/// Parses a body in as much detail as asked for.
+pub(crate) fn parse_body_features(body: &[u8], tiles: &[Tile], detail: Detail) -> Result<(Vec<TileFeature>, ParseReport), FetchTilesError> {
+    match detail {
+        Detail::Ids => parse_body::<FeatureId>(body, tiles),
+        Detail::Positions => parse_body::<PositionedFeature>(body, tiles),
+        Detail::Labels => parse_body::<LabeledFeature>(body, tiles),
+    }
+}
+
+/// Parses tiles of responses, as split off by [`split_items`]. `tiles` are
+/// the tiles of the items, in order, or empty if they aren't known.
+pub(crate) fn parse_items_features(items: &[Bytes], tiles: &[Tile], detail: Detail) -> Result<(Vec<TileFeature>, ParseReport), FetchTilesError> {
+    let mut report = ParseReport::default();
+    let mut features = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let item = std::str::from_utf8(item)
+            .map_err(|e| FetchTilesError::InvalidResponse(e.to_string()))?;
+        let tiles = match tiles.len() == items.len() {
+            true => std::slice::from_ref(&tiles[i]),
+            false => &[],
+        };
+        features.extend(match detail {
+            Detail::Ids => collect(vec![parse_item::<FeatureId>(item, &mut report)], tiles, &mut report),
+            Detail::Positions => collect(vec![parse_item::<PositionedFeature>(item, &mut report)], tiles, &mut report),
+            Detail::Labels => collect(vec![parse_item::<LabeledFeature>(item, &mut report)], tiles, &mut report),
+        });
+    }
+    Ok((features, report))
+}
+
+/// Splits a response into its per-tile items, without parsing them.
+pub(crate) fn split_items(body: &[u8]) -> Result<Vec<&RawValue>, FetchTilesError> {
+    Ok(split_root(body)?.unwrap_or_default())
+}
+
+/// Like [`split_items`], but `None` if the root isn't an array.
+fn split_root(body: &[u8]) -> Result<Option<Vec<&RawValue>>, FetchTilesError> {
+    let root: &RawValue = serde_json::from_slice(strip_xssi_prefix(body))?;
+    Ok(serde_json::from_str(root.get()).ok())
+}
+
+/// Strips the `)]}'` line some Google endpoints put in front of JSON to
+/// keep it from being loaded as a script.
+fn strip_xssi_prefix(body: &[u8]) -> &[u8] {
+    let trimmed = body.trim_ascii_start();
+    match trimmed.strip_prefix(b")]}'") {
+        Some(rest) => rest.strip_prefix(b",").unwrap_or(rest),
+        None => body,
+    }
+}
+
+fn parse_body<'a, F: RawFeature<'a>>(body: &'a [u8], tiles: &[Tile]) -> Result<(Vec<TileFeature>, ParseReport), FetchTilesError> {
+    let body = strip_xssi_prefix(body);
+    let mut report = ParseReport::default();
+
+    // Well-formed responses parse in a single pass. Anything else goes through
+    // the slower path, which keeps whatever it can read.
+    if let Ok(items) = serde_json::from_slice::<Vec<Item<F>>>(body) {
+        let features = collect(items, tiles, &mut report);
+        return Ok((features, report));
+    }
+
+    let Some(items) = split_root(body)? else {
+        report.non_array_roots += 1;
+        return Ok((Vec::new(), report));
+    };
+    let items: Vec<Item<F>> = items.into_iter()
+        .map(|item| parse_item(item.get(), &mut report))
+        .collect();
+    let features = collect(items, tiles, &mut report);
+    Ok((features, report))
+}
+
+/// Parses an item feature by feature, counting features that aren't even
+/// the right JSON type as skipped.
+fn parse_item<'a, F: RawFeature<'a>>(item: &'a str, report: &mut ParseReport) -> Item<'a, F> {
+    if let Ok(item) = serde_json::from_str(item) {
+        return item;
+    }
+
+    #[derive(Deserialize)]
+    struct RawItem<'a> {
+        #[serde(borrow, default)]
+        id: Option<&'a RawValue>,
+        #[serde(borrow)]
+        features: Option<Vec<&'a RawValue>>,
+    }
+
+    let Ok(raw) = serde_json::from_str::<RawItem>(item) else {
+        return Item { id: None, features: None };
+    };
+    let features = raw.features
+        .map(|features| features.into_iter()
+            .filter_map(|feature| {
+                let parsed = serde_json::from_str(feature.get()).ok();
+                if parsed.is_none() {
+                    report.skipped_features += 1;
+                }
+                parsed
+            })
+            .collect());
+    Item { id: raw.id, features }
+}
+
+/// Collects the features of `items`. Items are placed on the tile their ID
+/// names, or else on the tile requested at the same position.
+fn collect<'a, F: RawFeature<'a>>(items: Vec<Item<'a, F>>, tiles: &[Tile], report: &mut ParseReport) -> Vec<TileFeature> {
+    let by_position = items.len() == tiles.len();
+    let mut features = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        let tile = item.tile().or_else(|| by_position.then(|| tiles[i].clone()));
+        let Some(item_features) = item.features else {
+            report.unknown_shapes += 1;
+            continue;
+        };
+        for feature in item_features {
+            match feature.into_feature(tile.as_ref()) {
+                Some(feature) => features.push(feature),
+                None => report.skipped_features += 1,
+            }
+        }
+    }
+    features
+}
+
+fn feature_label(content: &str) -> Option<String> {
+    if let Ok(parsed) = serde_json::from_str::<Value>(content) {
+        if let Some(title) = find_title(&parsed) {
+            return Some(title);
+        }
+    }
+
+    let raw = &TITLE_REGEX.captures(content)?[1];
+    let unescaped = raw.replace("\\\"", "\"");
+    Some(serde_json::from_str::<String>(&format!("\"{}\"", unescaped)).unwrap_or(unescaped))
+}
+
+fn find_title(value: &Value) -> Option<String> {
+    match value {
+        Value::Object(map) => map.get("title")
+            .and_then(|title| title.as_str())
+            .map(|title| title.to_string())
+            .or_else(|| map.values().find_map(find_title)),
+        Value::Array(items) => items.iter().find_map(find_title),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_features() {
+        let body = br#"[{"features":[
+            {"id":"15074921902713971043","c":"{1:{title:\"Empire State Building\"}}"},
+            {"id":"15074921902713971044","c":"{\"1\":{\"title\":\"Caf\\u00e9\"}}"},
+            {"id":"15074921902713971045"},
+            {"id":"not-a-number"}
+        ]}]"#;
+
+        let feature = |id: &str, label: Option<&str>| TileFeature {
+            id: id.to_string(),
+            label: label.map(str::to_string),
+            tile: None,
+            lat_lng: None,
+        };
+        assert_eq!(parse_features(body, &[]).unwrap(), vec![
+            feature("d134e199a405a163", Some("Empire State Building")),
+            feature("d134e199a405a164", Some("Café")),
+            feature("d134e199a405a165", None),
+        ]);
+        let (ids, report) = parse_ids_with_report(body).unwrap();
+        assert_eq!(ids, vec!["d134e199a405a163", "d134e199a405a164", "d134e199a405a165"]);
+        assert_eq!(report, ParseReport { skipped_features: 1, ..Default::default() });
+    }
+
+    #[test]
+    fn test_parse_positions() {
+        let esb = Tile { x: 19299, y: 24631, zoom: 16 };
+        let next = Tile { x: 19300, y: 24631, zoom: 16 };
+        let body = br#"[
+            {"features":[{"id":"15074921902713971043","a":[128,128]},{"id":"15074921902713971044"}]},
+            {"id":"16/19300/24631","features":[{"id":"15074921902713971045","a":[0,256,1]}]}
+        ]"#;
+
+        // The first item is placed by request order, the second by its own ID
+        let (features, report) = parse_positions_with_report(body, &[esb.clone(), Tile { x: 0, y: 0, zoom: 16 }]).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(features[0].tile, Some(esb.clone()));
+        assert_eq!(features[0].lat_lng, Some(esb.center()));
+        assert_eq!(features[1].lat_lng, None);
+        assert_eq!(features[2].tile, Some(next.clone()));
+        assert_eq!(features[2].lat_lng, Some(next.to_lat_lng(0.0, 1.0)));
+
+        let (features, _) = parse_positions_with_report(body, &[]).unwrap();
+        assert_eq!(features[0].tile, None);
+        assert_eq!(features[2].tile, Some(next));
+    }
+
+    #[test]
+    fn test_parse_unexpected_shapes() {
+        // Odd items and features are skipped rather than failing the response
+        let body = br#"[
+            {"features":[{"id":15074921902713971043},{"id":"15074921902713971044"}]},
+            {"features":{"id":"15074921902713971045"}},
+            "tile",
+            {"features":[{"id":"15074921902713971046","c":1}]}
+        ]"#;
+        let (ids, report) = parse_ids_with_report(body).unwrap();
+        assert_eq!(ids, vec!["d134e199a405a164", "d134e199a405a166"]);
+        assert_eq!(report, ParseReport { skipped_features: 1, non_array_roots: 0, unknown_shapes: 2, not_cached: 0 });
+
+        let (ids, report) = parse_ids_with_report(br#"{"error":"unavailable"}"#).unwrap();
+        assert!(ids.is_empty());
+        assert_eq!(report, ParseReport { skipped_features: 0, non_array_roots: 1, unknown_shapes: 0, not_cached: 0 });
+        assert!(parse_ids(br#"[{"features":["#).is_err());
+    }
+
+    #[test]
+    fn test_strip_xssi_prefix() {
+        let body = b")]}'\n[{\"features\":[{\"id\":\"15074921902713971043\"}]}]";
+        assert_eq!(parse_ids(body).unwrap(), vec!["d134e199a405a163"]);
+        assert_eq!(split_items(body).unwrap().len(), 1);
+        assert_eq!(strip_xssi_prefix(b"[]"), b"[]");
+    }
+}
@@ -1,49 +1,447 @@
 use hyper_tls::HttpsConnector;
 use hyper::{Request, Method};
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use bytes::Bytes;
+use serde::Serialize;
 use http_body_util::{BodyExt, Empty};
 use hyper_util::{client::legacy::Client, client::legacy::connect::HttpConnector};
-use serde_json::Value;
-
-
-pub async fn view_tiles(client: &Client<HttpsConnector<HttpConnector>, Empty<Bytes>>, tiles: &Vec<crate::tiles::types::Tile>) -> Result<Vec<String>, crate::tiles::error::FetchTilesError> {
-    
-    let pb = crate::tiles::format::format_tiles(tiles);
-
-    let req = Request::builder()
-        .method(Method::GET)
-        .uri(format!("https://maps.googleapis.com/maps/vt?pb={}", pb))
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 6.1; rv:31.0) Gecko/20100101 Firefox/31.0")
-        .body(Empty::new())?;
-
-    let resp = client.request(req).await?;
-
-    let status = resp.status();
-    if status != hyper::StatusCode::OK {
-        return Err(crate::tiles::error::FetchTilesError::UnexpectedStatusCode(status.as_u16()));
-    }
-
-    let body_bytes = resp.into_body().collect().await?.to_bytes();
-    let body = String::from_utf8(body_bytes.to_vec())?;
-
-    let parsed: Value = serde_json::from_str(&body)?;
-    let mut feature_ids = Vec::new();
-    
-    if let Some(array) = parsed.as_array() {
-        for item in array {
-            if let Some(features) = item["features"].as_array() {
-                for feature in features {
-                    if let Some(id) = feature["id"].as_str() {
-                        // Convert id to a number (assuming it's numeric)
-                        if let Ok(id_num) = id.parse::<u64>() {
-                            // Convert to base16 (hexadecimal) and store as string
-                            let base16_id = format!("{:x}", id_num);
-                            feature_ids.push(base16_id);
-                        }
-                    }
+use crate::tiles::archive::TileArchive;
+use crate::tiles::cache::{cache_key, TileCache};
+use crate::tiles::parse::{parse_body_features, parse_items_features, split_items, Detail, ParseReport};
+use crate::tiles::types::{Tile, TileFeature};
+use crate::tiles::version::{parse_map_version, MapVersionCache, MAP_VERSION_BOOTSTRAP_PATH};
+use crate::vcr::{Vcr, VcrMode};
+
+pub const TILES_API_URL: &str = "https://maps.googleapis.com";
+
+/// How long the outcome of a map version discovery is reused, see
+/// [`TilesClient::map_version_refresh_cooldown`].
+pub const DEFAULT_MAP_VERSION_REFRESH_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Response bytes received by a [`TilesClient`] and its clones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ByteCounts {
+    /// Bytes on the wire, compressed if the server compressed them.
+    pub received: u64,
+    /// Bytes after decompression.
+    pub decoded: u64,
+}
+
+/// The last discovery made for a stale map version, shared by all clones.
+struct VersionCheck {
+    stale: String,
+    checked_at: Instant,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct ByteCounters {
+    received: AtomicU64,
+    decoded: AtomicU64,
+}
+
+/// Fetches vector tiles from `base_url`, which defaults to [`TILES_API_URL`].
+#[derive(Clone)]
+pub struct TilesClient {
+    client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>,
+    base_url: String,
+    language: String,
+    region: String,
+    map_version: Arc<RwLock<String>>,
+    bootstrap_url: Option<String>,
+    map_version_cache: Option<MapVersionCache>,
+    refreshing: Arc<tokio::sync::Mutex<Option<VersionCheck>>>,
+    refresh_cooldown: Duration,
+    vcr: Option<Vcr>,
+    cache: Option<TileCache>,
+    archive: Option<TileArchive>,
+    parse_blocking_above: Option<usize>,
+    strict: bool,
+    compression: bool,
+    bytes: Arc<ByteCounters>,
+}
+
+impl TilesClient {
+    pub fn new(client: Client<HttpsConnector<HttpConnector>, Empty<Bytes>>) -> Self {
+        Self {
+            client,
+            base_url: TILES_API_URL.to_string(),
+            language: crate::DEFAULT_LANGUAGE.to_string(),
+            region: crate::DEFAULT_REGION.to_string(),
+            map_version: Arc::new(RwLock::new(crate::tiles::format::DEFAULT_MAP_VERSION.to_string())),
+            bootstrap_url: None,
+            map_version_cache: None,
+            refreshing: Arc::new(tokio::sync::Mutex::new(None)),
+            refresh_cooldown: DEFAULT_MAP_VERSION_REFRESH_COOLDOWN,
+            vcr: None,
+            cache: None,
+            archive: None,
+            parse_blocking_above: None,
+            strict: false,
+            compression: true,
+            bytes: Arc::new(ByteCounters::default()),
+        }
+    }
+
+    /// Points the client at another server, e.g. a local fake in tests.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the language of feature labels. Defaults to `en-US`.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    /// Sets the region the tiles are requested from. Defaults to `US`.
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Requests tiles of map version `map_version` instead of
+    /// [`DEFAULT_MAP_VERSION`](crate::tiles::DEFAULT_MAP_VERSION).
+    pub fn map_version(mut self, map_version: impl Into<String>) -> Self {
+        self.map_version = Arc::new(RwLock::new(map_version.into()));
+        self
+    }
+
+    /// Discovers the map version from `url` instead of
+    /// `{base_url}/maps/api/js`, see [`discover_map_version`](Self::discover_map_version).
+    pub fn bootstrap_url(mut self, url: impl Into<String>) -> Self {
+        self.bootstrap_url = Some(url.into());
+        self
+    }
+
+    /// Saves versions found by [`refresh_map_version`](Self::refresh_map_version) to `cache`.
+    pub fn map_version_cache(mut self, cache: MapVersionCache) -> Self {
+        self.map_version_cache = Some(cache);
+        self
+    }
+
+    /// How long [`refresh_map_version`](Self::refresh_map_version) reuses a
+    /// discovery that failed or found nothing newer. Defaults to
+    /// [`DEFAULT_MAP_VERSION_REFRESH_COOLDOWN`].
+    pub fn map_version_refresh_cooldown(mut self, cooldown: Duration) -> Self {
+        self.refresh_cooldown = cooldown;
+        self
+    }
+
+    /// Records to or replays from a cassette, see [`Vcr`](crate::vcr::Vcr).
+    pub fn vcr(mut self, vcr: Vcr) -> Self {
+        self.vcr = Some(vcr);
+        self
+    }
+
+    /// Serves tiles from `cache` where possible and stores fetched tiles in it.
+    pub fn cache(mut self, cache: TileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Appends every fetched response to `archive`. Cache hits aren't archived
+    /// again.
+    pub fn archive(mut self, archive: TileArchive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Parses response bodies of at least `bytes` on the blocking thread pool
+    /// instead of the calling task. Off by default.
+    pub fn parse_blocking_above(mut self, bytes: usize) -> Self {
+        self.parse_blocking_above = Some(bytes);
+        self
+    }
+
+    /// Fails with [`UnexpectedShape`](crate::tiles::FetchTilesError::UnexpectedShape)
+    /// instead of skipping anything the parser doesn't recognize, see [`ParseReport`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Asks for gzip or brotli compressed responses. On by default.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Bytes received so far by this client and its clones. Cache hits and
+    /// replayed responses aren't counted.
+    pub fn byte_counts(&self) -> ByteCounts {
+        ByteCounts {
+            received: self.bytes.received.load(Ordering::Relaxed),
+            decoded: self.bytes.decoded.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The map version requested by this client and its clones.
+    pub fn current_map_version(&self) -> String {
+        self.map_version.read().unwrap().clone()
+    }
+
+    /// Switches this client and its clones to `map_version`.
+    pub fn set_map_version(&self, map_version: impl Into<String>) {
+        *self.map_version.write().unwrap() = map_version.into();
+    }
+
+    /// Fetches the bootstrap page and returns the newest map version it
+    /// mentions. Doesn't change the version in use. Cassettes don't hold the
+    /// bootstrap page, so this fails with `NotRecorded` when replaying.
+    pub async fn discover_map_version(&self) -> Result<String, crate::tiles::error::FetchTilesError> {
+        let url = self.bootstrap_url.clone()
+            .unwrap_or_else(|| format!("{}{}", self.base_url, MAP_VERSION_BOOTSTRAP_PATH));
+        if let Some(vcr) = self.vcr.as_ref().filter(|vcr| vcr.mode() == VcrMode::Replay) {
+            return Err(crate::tiles::error::FetchTilesError::NotRecorded(format!("{} in {}", url, vcr.dir().display())));
+        }
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&url)
+            .header("User-Agent", "Mozilla/5.0 (Windows NT 6.1; rv:31.0) Gecko/20100101 Firefox/31.0")
+            .header(hyper::header::ACCEPT_ENCODING, crate::tiles::encoding::ACCEPT_ENCODING);
+
+        let resp = self.client.request(req.body(Empty::new())?).await?;
+        let status = resp.status().as_u16();
+        let content_encoding = resp.headers().get(hyper::header::CONTENT_ENCODING)
+            .and_then(|encoding| encoding.to_str().ok())
+            .map(|encoding| encoding.to_string());
+        let body = resp.into_body().collect().await?.to_bytes();
+        if status != hyper::StatusCode::OK {
+            return Err(crate::tiles::error::FetchTilesError::UnexpectedStatusCode(status));
+        }
+        let body = crate::tiles::encoding::decode_body(content_encoding.as_deref(), body)
+            .map_err(crate::tiles::error::FetchTilesError::DecompressionError)?;
+
+        parse_map_version(&String::from_utf8_lossy(&body))
+            .ok_or(crate::tiles::error::FetchTilesError::MapVersionNotFound(url))
+    }
+
+    /// Replaces `stale` with a newly discovered map version and returns the
+    /// version now in use. If another clone already replaced `stale`, returns
+    /// its version without discovering again. Returns `stale` itself if
+    /// nothing newer was found.
+    ///
+    /// A 4xx doesn't always mean the version is stale, so a discovery that
+    /// failed or found nothing newer is reused by every caller for the
+    /// [cooldown](Self::map_version_refresh_cooldown) instead of being repeated.
+    pub async fn refresh_map_version(&self, stale: &str) -> Result<String, crate::tiles::error::FetchTilesError> {
+        let mut last_check = self.refreshing.lock().await;
+        let current = self.current_map_version();
+        if current != stale {
+            return Ok(current);
+        }
+        if let Some(check) = last_check.as_ref()
+            .filter(|check| check.stale == stale && check.checked_at.elapsed() < self.refresh_cooldown)
+        {
+            return match &check.error {
+                Some(error) => Err(crate::tiles::error::FetchTilesError::MapVersionRefreshFailed(error.clone())),
+                None => Ok(current),
+            };
+        }
+
+        let discovered = self.discover_map_version().await;
+        *last_check = Some(VersionCheck {
+            stale: stale.to_string(),
+            checked_at: Instant::now(),
+            error: discovered.as_ref().err().map(|e| e.to_string()),
+        });
+        let discovered = discovered?;
+        if discovered != current {
+            self.set_map_version(discovered.clone());
+            if let Some(cache) = &self.map_version_cache {
+                cache.store(&discovered).await
+                    .map_err(crate::tiles::error::FetchTilesError::MapVersionCacheError)?;
+            }
+        }
+        Ok(discovered)
+    }
+
+    pub async fn view_tiles(&self, tiles: &Vec<Tile>) -> Result<Vec<String>, crate::tiles::error::FetchTilesError> {
+        Ok(self.view_tiles_with_report(tiles).await?.0)
+    }
+
+    /// Like [`view_tiles`](Self::view_tiles), but also reports what the parser skipped.
+    pub async fn view_tiles_with_report(&self, tiles: &Vec<Tile>) -> Result<(Vec<String>, ParseReport), crate::tiles::error::FetchTilesError> {
+        let (features, report) = self.features(tiles, Detail::Ids).await?;
+        Ok((features.into_iter().map(|feature| feature.id).collect(), report))
+    }
+
+    /// Like [`view_tiles_with_report`](Self::view_tiles_with_report), but returns
+    /// each feature's tile and position instead of just its ID.
+    pub async fn view_tile_positions(&self, tiles: &Vec<Tile>) -> Result<(Vec<TileFeature>, ParseReport), crate::tiles::error::FetchTilesError> {
+        self.features(tiles, Detail::Positions).await
+    }
+
+    /// Like [`view_tiles`](Self::view_tiles), but also returns each feature's
+    /// label, tile and position.
+    pub async fn view_tile_features(&self, tiles: &Vec<Tile>) -> Result<Vec<TileFeature>, crate::tiles::error::FetchTilesError> {
+        Ok(self.features(tiles, Detail::Labels).await?.0)
+    }
+
+    async fn features(&self, tiles: &Vec<Tile>, detail: Detail) -> Result<(Vec<TileFeature>, ParseReport), crate::tiles::error::FetchTilesError> {
+        let (features, report) = self.fetch_features(tiles, detail).await?;
+        if self.strict && !report.is_clean() {
+            return Err(crate::tiles::error::FetchTilesError::UnexpectedShape(report));
+        }
+        Ok((features, report))
+    }
+
+    async fn fetch_features(&self, tiles: &Vec<Tile>, detail: Detail) -> Result<(Vec<TileFeature>, ParseReport), crate::tiles::error::FetchTilesError> {
+        // Read once, so a refresh mid-request can't mix versions.
+        let map_version = self.current_map_version();
+        let Some(cache) = &self.cache else {
+            let body = self.fetch(tiles, &map_version).await?;
+            let tiles = tiles.clone();
+            return self.parse(body.len(), move || parse_body_features(&body, &tiles, detail)).await;
+        };
+
+        let keys: Vec<String> = tiles.iter()
+            .map(|tile| cache_key(tile, &self.language, &self.region, &map_version))
+            .collect();
+        let mut items = cache.get_many(keys.clone()).await?;
+        let missing: Vec<usize> = (0..tiles.len()).filter(|&i| items[i].is_none()).collect();
+
+        if !missing.is_empty() {
+            // Offline, the cached tiles are still worth parsing
+            if cache.is_cache_only() {
+                let not_cached = missing.len();
+                let cached: Vec<(Tile, Bytes)> = tiles.iter().cloned()
+                    .zip(items)
+                    .filter_map(|(tile, item)| Some((tile, item?)))
+                    .collect();
+                let (cached_tiles, cached): (Vec<Tile>, Vec<Bytes>) = cached.into_iter().unzip();
+                let size = cached.iter().map(Bytes::len).sum();
+                return self.parse(size, move || {
+                    let (features, mut report) = parse_items_features(&cached, &cached_tiles, detail)?;
+                    report.not_cached = not_cached;
+                    Ok((features, report))
+                }).await;
+            }
+
+            let missing_tiles: Vec<Tile> = missing.iter().map(|&i| tiles[i].clone()).collect();
+            let body = self.fetch(&missing_tiles, &map_version).await?;
+            let fetched: Vec<Bytes> = split_items(&body)?.into_iter()
+                .map(|item| Bytes::copy_from_slice(item.get().as_bytes()))
+                .collect();
+
+            // Without one item per tile the items can't be cached per tile.
+            if fetched.len() != missing.len() {
+                let (cached_tiles, cached): (Vec<Tile>, Vec<Bytes>) = tiles.iter().cloned()
+                    .zip(items)
+                    .filter_map(|(tile, item)| Some((tile, item?)))
+                    .unzip();
+                let size = body.len() + cached.iter().map(Bytes::len).sum::<usize>();
+                return self.parse(size, move || {
+                    let (mut features, mut report) = parse_items_features(&cached, &cached_tiles, detail)?;
+                    let (fetched, fetched_report) = parse_body_features(&body, &missing_tiles, detail)?;
+                    features.extend(fetched);
+                    report += fetched_report;
+                    Ok((features, report))
+                }).await;
+            }
+
+            let mut entries = Vec::with_capacity(missing.len());
+            for (&i, item) in missing.iter().zip(fetched) {
+                entries.push((keys[i].clone(), item.clone()));
+                items[i] = Some(item);
+            }
+            cache.insert_many(entries).await?;
+        }
+
+        let items: Vec<Bytes> = items.into_iter().flatten().collect();
+        let size = items.iter().map(Bytes::len).sum();
+        let tiles = tiles.clone();
+        self.parse(size, move || parse_items_features(&items, &tiles, detail)).await
+    }
+
+    /// Runs `parse` on the blocking thread pool if `size` is over the threshold.
+    async fn parse<T: Send + 'static>(
+        &self,
+        size: usize,
+        parse: impl FnOnce() -> Result<T, crate::tiles::error::FetchTilesError> + Send + 'static,
+    ) -> Result<T, crate::tiles::error::FetchTilesError> {
+        match self.parse_blocking_above {
+            Some(threshold) if size >= threshold => tokio::task::spawn_blocking(parse).await?,
+            _ => parse(),
+        }
+    }
+
+    /// Requests `tiles` in a single call and returns the response body.
+    async fn fetch(&self, tiles: &Vec<Tile>, map_version: &str) -> Result<Bytes, crate::tiles::error::FetchTilesError> {
+
+        let pb = crate::tiles::format::format_tiles(tiles, &self.language, &self.region, map_version);
+
+        let path = format!("/maps/vt?pb={}", pb);
+        let (status, body_bytes) = match &self.vcr {
+            Some(vcr) if vcr.mode() == VcrMode::Replay => vcr.replay_tile(&path).await?
+                .ok_or_else(|| crate::tiles::error::FetchTilesError::NotRecorded(format!("{} in {}", path, vcr.dir().display())))?,
+            _ => {
+                let mut req = Request::builder()
+                    .method(Method::GET)
+                    .uri(format!("{}{}", self.base_url, path))
+                    .header("User-Agent", "Mozilla/5.0 (Windows NT 6.1; rv:31.0) Gecko/20100101 Firefox/31.0");
+                if self.compression {
+                    req = req.header(hyper::header::ACCEPT_ENCODING, crate::tiles::encoding::ACCEPT_ENCODING);
                 }
+
+                let resp = self.client.request(req.body(Empty::new())?).await?;
+                let status = resp.status().as_u16();
+                let content_encoding = resp.headers().get(hyper::header::CONTENT_ENCODING)
+                    .and_then(|encoding| encoding.to_str().ok())
+                    .map(|encoding| encoding.to_string());
+                let received = resp.into_body().collect().await?.to_bytes();
+                let received_len = received.len() as u64;
+                let body_bytes = crate::tiles::encoding::decode_body(content_encoding.as_deref(), received)
+                    .map_err(crate::tiles::error::FetchTilesError::DecompressionError)?;
+                self.bytes.received.fetch_add(received_len, Ordering::Relaxed);
+                self.bytes.decoded.fetch_add(body_bytes.len() as u64, Ordering::Relaxed);
+
+                if let Some(vcr) = &self.vcr {
+                    vcr.record_tile(&path, status, &body_bytes).await?;
+                }
+                (status, body_bytes)
             }
+        };
+
+        // Retired versions are rejected as bad requests rather than rate limited,
+        // but so are bad requests. Only a newer version means this one is stale.
+        if matches!(status, 400 | 404 | 410) {
+            return Err(match self.refresh_map_version(map_version).await {
+                Ok(current) if current != map_version => crate::tiles::error::FetchTilesError::StaleMapVersion(map_version.to_string()),
+                Ok(_) => crate::tiles::error::FetchTilesError::UnexpectedStatusCode(status),
+                Err(e) => {
+                    tracing::warn!("Status {} for map version {}, and no newer version could be discovered: {}", status, map_version, e);
+                    crate::tiles::error::FetchTilesError::UnexpectedStatusCode(status)
+                }
+            });
+        }
+        if status != hyper::StatusCode::OK {
+            return Err(crate::tiles::error::FetchTilesError::UnexpectedStatusCode(status));
         }
+
+        if let Some(archive) = &self.archive {
+            archive.write(tiles, &body_bytes).await
+                .map_err(crate::tiles::error::FetchTilesError::ArchiveError)?;
+        }
+
+        Ok(body_bytes)
     }
-    Ok(feature_ids)
-}
\ No newline at end of file
+}
+
+pub async fn view_tiles(client: &Client<HttpsConnector<HttpConnector>, Empty<Bytes>>, tiles: &Vec<Tile>) -> Result<Vec<String>, crate::tiles::error::FetchTilesError> {
+    TilesClient::new(client.clone()).view_tiles(tiles).await
+}
+
+/// Like [`view_tiles`], but also returns each feature's label, localized
+/// for `language` and `region`.
+pub async fn view_tile_features(client: &Client<HttpsConnector<HttpConnector>, Empty<Bytes>>, tiles: &Vec<Tile>, language: &str, region: &str) -> Result<Vec<TileFeature>, crate::tiles::error::FetchTilesError> {
+    TilesClient::new(client.clone())
+        .language(language)
+        .region(region)
+        .view_tile_features(tiles)
+        .await
+}
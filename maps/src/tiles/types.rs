@@ -1,6 +1,105 @@
-#[derive(Clone)]
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use serde::Serialize;
+
+/// Tiles have zoom levels below this, so `x` and `y` fit in 32 bits.
+pub const ZOOM_LIMIT: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Tile {
     pub x: usize,
     pub y: usize,
     pub zoom: usize,
-}
\ No newline at end of file
+}
+
+impl Tile {
+    /// Returns the Web Mercator tile containing the given point, or an error
+    /// if `zoom` isn't below [`ZOOM_LIMIT`].
+    pub fn from_lat_lng(latitude: f64, longitude: f64, zoom: usize) -> Result<Self, String> {
+        if zoom >= ZOOM_LIMIT {
+            return Err(format!("Zoom {} is out of range", zoom));
+        }
+        let n = (1u64 << zoom) as f64;
+        let lat = latitude.clamp(-85.05112878, 85.05112878).to_radians();
+        let x = ((longitude + 180.0) / 360.0 * n).floor();
+        let y = ((1.0 - lat.tan().asinh() / PI) / 2.0 * n).floor();
+
+        Ok(Self {
+            x: x.clamp(0.0, n - 1.0) as usize,
+            y: y.clamp(0.0, n - 1.0) as usize,
+            zoom,
+        })
+    }
+
+    /// Converts a position within the tile, as fractions of its width and
+    /// height, to `(latitude, longitude)`.
+    pub fn to_lat_lng(&self, fx: f64, fy: f64) -> (f64, f64) {
+        // Fields are public, so the zoom may be out of range. `exp2` can't overflow.
+        let n = (self.zoom as f64).exp2();
+        let longitude = (self.x as f64 + fx) / n * 360.0 - 180.0;
+        let latitude = (PI * (1.0 - 2.0 * (self.y as f64 + fy) / n)).sinh().atan().to_degrees();
+        (latitude, longitude)
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        self.to_lat_lng(0.5, 0.5)
+    }
+}
+
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.zoom, self.x, self.y)
+    }
+}
+
+impl FromStr for Tile {
+    type Err = String;
+
+    /// Parses `zoom/x/y`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<usize> = s.split('/')
+            .map(|part| part.trim().parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid tile {}: {}", s, e))?;
+
+        match parts[..] {
+            [zoom, x, y] if zoom < ZOOM_LIMIT && x < 1 << zoom && y < 1 << zoom => Ok(Self { x, y, zoom }),
+            [_, _, _] => Err(format!("Tile {} is out of range", s)),
+            _ => Err(format!("Invalid tile {}: expected zoom/x/y", s)),
+        }
+    }
+}
+
+/// A feature returned by [`view_tile_features`](crate::tiles::view_tile_features).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TileFeature {
+    /// The feature ID in hex, as used by `get_place`.
+    pub id: String,
+    pub label: Option<String>,
+    /// The tile the feature was found in, if the response names it or the
+    /// requested tiles show it.
+    pub tile: Option<Tile>,
+    /// `(latitude, longitude)` of the point the feature is drawn at, if it
+    /// has one and its tile is known.
+    pub lat_lng: Option<(f64, f64)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_lat_lng() {
+        let tile = Tile::from_lat_lng(40.7484405, -73.9856644, 16).unwrap();
+        assert_eq!(tile, Tile { x: 19299, y: 24631, zoom: 16 });
+        assert_eq!(tile.to_string().parse::<Tile>(), Ok(tile.clone()));
+        assert_eq!(Tile::from_lat_lng(tile.center().0, tile.center().1, 16), Ok(tile));
+        assert_eq!(Tile::from_lat_lng(0.0, 0.0, 31).unwrap().x, 1 << 30);
+        assert!(Tile::from_lat_lng(0.0, 0.0, 32).is_err());
+        assert!(Tile::from_lat_lng(0.0, 0.0, 64).is_err());
+        assert!(Tile { x: 0, y: 0, zoom: 64 }.center().1.is_finite());
+        assert!("16/19295".parse::<Tile>().is_err());
+        assert!("1/2/0".parse::<Tile>().is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    // Tile URL templates carry the version as `!2sm!3i<version>`, older ones as `m@<version>`.
+    static ref MAP_VERSION_REGEX: Regex = Regex::new(r"(?:!2sm!3i|\bm@)(\d{6,})").unwrap();
+}
+
+/// Path of the page the map version is discovered from, relative to the
+/// tiles API URL. The Maps JavaScript API loader embeds the tile URL templates.
+pub const MAP_VERSION_BOOTSTRAP_PATH: &str = "/maps/api/js";
+
+/// Finds the map version in a bootstrap page or tile URL. If the page
+/// mentions several versions, the newest one wins.
+pub fn parse_map_version(page: &str) -> Option<String> {
+    MAP_VERSION_REGEX.captures_iter(page)
+        .filter_map(|cap| cap[1].parse::<u64>().ok())
+        .max()
+        .map(|version| version.to_string())
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedMapVersion {
+    version: String,
+    discovered_at: u64,
+}
+
+/// A discovered map version saved to a JSON file, so restarts don't have to
+/// discover it again while it's younger than `max_age`.
+#[derive(Debug, Clone)]
+pub struct MapVersionCache {
+    path: Arc<PathBuf>,
+    max_age: Duration,
+}
+
+impl MapVersionCache {
+    pub fn new(path: impl AsRef<Path>, max_age: Duration) -> Self {
+        Self {
+            path: Arc::new(path.as_ref().to_path_buf()),
+            max_age,
+        }
+    }
+
+    /// Returns the saved version, or `None` if there is none or it's too old.
+    pub async fn load(&self) -> io::Result<Option<String>> {
+        let json = match tokio::fs::read(self.path.as_path()).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let cached: CachedMapVersion = serde_json::from_slice(&json)?;
+        let fresh = now_secs().saturating_sub(cached.discovered_at) < self.max_age.as_secs();
+        Ok(fresh.then_some(cached.version))
+    }
+
+    pub async fn store(&self, version: &str) -> io::Result<()> {
+        let cached = CachedMapVersion {
+            version: version.to_string(),
+            discovered_at: now_secs(),
+        };
+        tokio::fs::write(self.path.as_path(), serde_json::to_vec_pretty(&cached)?).await
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_map_version() {
+        let page = r#"["https://maps.googleapis.com/maps/vt?pb=!1m5!1m4!1i{z}!2i{x}!3i{y}!4i256!2m3!1e0!2sm!3i702451461!3m9"],"m@702451400""#;
+        assert_eq!(parse_map_version(page).as_deref(), Some("702451461"));
+        assert_eq!(parse_map_version("google.maps.Load(apiLoad)").as_deref(), None);
+    }
+
+    #[tokio::test]
+    async fn test_map_version_cache() {
+        let path = std::env::temp_dir().join(format!("maps-version-{}.json", std::process::id()));
+        let cache = MapVersionCache::new(&path, Duration::from_secs(3600));
+
+        assert_eq!(cache.load().await.unwrap(), None);
+        cache.store("702451461").await.unwrap();
+        assert_eq!(cache.load().await.unwrap().as_deref(), Some("702451461"));
+        assert_eq!(MapVersionCache::new(&path, Duration::ZERO).load().await.unwrap(), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
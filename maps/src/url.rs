@@ -0,0 +1,311 @@
+//! Parsing and building Google Maps URLs.
+
+use std::fmt;
+use std::str::FromStr;
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+use urlencoding::{decode, encode};
+use crate::Place;
+
+lazy_static! {
+    static ref REDIRECT_REGEX: Regex = Regex::new(r"/url\?q=([^&]*)(?:&|$)").unwrap();
+    static ref PLACE_REGEX: Regex = Regex::new(r"/maps/place/([^/@?]+)").unwrap();
+    static ref SEARCH_REGEX: Regex = Regex::new(r"/maps/search/([^/@?]+)").unwrap();
+    static ref VIEWPORT_REGEX: Regex = Regex::new(r"/@(-?\d+(?:\.\d+)?),(-?\d+(?:\.\d+)?)(?:,(\d+(?:\.\d+)?)z)?").unwrap();
+    static ref FEATURE_REGEX: Regex = Regex::new(r"!1s0x([0-9a-fA-F]+):0x([0-9a-fA-F]+)").unwrap();
+    static ref COORDINATES_REGEX: Regex = Regex::new(r"!3d(-?\d+(?:\.\d+)?)!4d(-?\d+(?:\.\d+)?)").unwrap();
+    static ref CID_REGEX: Regex = Regex::new(r"[?&]cid=(\d+)").unwrap();
+    static ref HOST_REGEX: Regex = Regex::new(r"^(?:[a-zA-Z][a-zA-Z0-9+.-]*://)?([^/?#:]+)").unwrap();
+    static ref MAPS_HOST_REGEX: Regex = Regex::new(r"(?i)^(?:(?:www|maps)\.)?google(?:\.[a-z]{2,3}){1,2}$|^(?:maps\.app\.)?goo\.gl$").unwrap();
+}
+
+const DEFAULT_ZOOM: u8 = 17;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum UrlError {
+    #[error("Not a Google Maps URL: {0}")]
+    NotAMapsUrl(String),
+
+    #[error("Invalid feature ID: {0}")]
+    InvalidFeatureId(String),
+}
+
+/// A feature ID as it appears in Maps URLs (`0x0:0xd134e199a405a163`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeatureId {
+    pub cell_id: u64,
+    pub id: u64,
+}
+
+impl FeatureId {
+    /// Returns the ID in the hex form used by `get_place` and `locations-rs`,
+    /// e.g. `0d134e199a405a163`. The ID is padded to 16 digits, so the cell
+    /// ID is everything before the last 16.
+    pub fn location_id(&self) -> String {
+        format!("{:x}{:016x}", self.cell_id, self.id)
+    }
+
+    /// Parses a `get_place` location ID. IDs of up to 16 digits have a zero
+    /// cell ID.
+    pub fn from_location_id(location_id: &str) -> Result<Self, UrlError> {
+        let invalid = || UrlError::InvalidFeatureId(location_id.to_string());
+        if location_id.is_empty() || !location_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let (cell_id, id) = location_id.split_at(location_id.len().saturating_sub(16));
+        Ok(Self {
+            cell_id: match cell_id {
+                "" => 0,
+                cell_id => u64::from_str_radix(cell_id, 16).map_err(|_| invalid())?,
+            },
+            id: u64::from_str_radix(id, 16).map_err(|_| invalid())?,
+        })
+    }
+
+    /// The decimal `cid` used by `?cid=` URLs.
+    pub fn cid(&self) -> u64 {
+        self.id
+    }
+}
+
+impl fmt::Display for FeatureId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:x}:0x{:x}", self.cell_id, self.id)
+    }
+}
+
+impl FromStr for FeatureId {
+    type Err = UrlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || UrlError::InvalidFeatureId(s.to_string());
+        let (cell_id, id) = s.split_once(':').ok_or_else(invalid)?;
+        let parse = |part: &str| part.strip_prefix("0x")
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            .ok_or_else(invalid);
+        Ok(Self {
+            cell_id: parse(cell_id)?,
+            id: parse(id)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlKind {
+    /// `/maps/place/...`
+    Place,
+    /// `/maps/search/...`
+    Search,
+    /// `?cid=...`
+    Cid,
+    /// Any other Maps URL with a viewport, e.g. `/maps/@40.74,-73.98,17z`.
+    Viewport,
+}
+
+/// What could be extracted from a Maps URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapsUrl {
+    pub kind: UrlKind,
+    pub feature_id: Option<FeatureId>,
+    /// The place location if the URL has one, otherwise the viewport center.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub zoom: Option<f64>,
+    /// The place title, or the query for search URLs.
+    pub title: Option<String>,
+}
+
+/// Parses a Maps place, search, `?cid=` or viewport URL. Google redirect
+/// links (`/url?q=...`) are unwrapped first.
+pub fn parse(url: &str) -> Result<MapsUrl, UrlError> {
+    let unwrapped = unwrap_redirect(url);
+    let url = unwrapped.as_deref().unwrap_or(url);
+
+    let feature_id = FEATURE_REGEX.captures(url).and_then(|cap| {
+        Some(FeatureId {
+            cell_id: u64::from_str_radix(&cap[1], 16).ok()?,
+            id: u64::from_str_radix(&cap[2], 16).ok()?,
+        })
+    });
+    // `cid` is a common query parameter, so only trust it on Google hosts
+    let cid = CID_REGEX.captures(url)
+        .filter(|_| is_maps_host(url))
+        .and_then(|cap| cap[1].parse::<u64>().ok())
+        .map(|id| FeatureId { cell_id: 0, id });
+
+    let place_title = PLACE_REGEX.captures(url)
+        .map(|cap| cap[1].to_string())
+        .filter(|title| !title.starts_with("data="));
+    let query = SEARCH_REGEX.captures(url).map(|cap| cap[1].to_string());
+
+    let viewport = VIEWPORT_REGEX.captures(url);
+    let coordinates = COORDINATES_REGEX.captures(url)
+        .and_then(|cap| Some((cap[1].parse().ok()?, cap[2].parse().ok()?)))
+        .or_else(|| viewport.as_ref()
+            .and_then(|cap| Some((cap[1].parse().ok()?, cap[2].parse().ok()?))));
+    let zoom = viewport.as_ref()
+        .and_then(|cap| cap.get(3))
+        .and_then(|zoom| zoom.as_str().parse().ok());
+
+    let kind = if url.contains("/maps/place/") {
+        UrlKind::Place
+    } else if query.is_some() {
+        UrlKind::Search
+    } else if cid.is_some() {
+        UrlKind::Cid
+    } else if coordinates.is_some() && url.contains("/maps") {
+        UrlKind::Viewport
+    } else {
+        return Err(UrlError::NotAMapsUrl(url.to_string()));
+    };
+
+    Ok(MapsUrl {
+        kind,
+        feature_id: feature_id.or(cid),
+        latitude: coordinates.map(|(lat, _)| lat),
+        longitude: coordinates.map(|(_, lng)| lng),
+        zoom,
+        title: place_title.or(query).map(|title| decode_component(&title)),
+    })
+}
+
+/// Whether `url` is on a Google Maps host, e.g. `www.google.co.uk` or `maps.app.goo.gl`.
+fn is_maps_host(url: &str) -> bool {
+    HOST_REGEX.captures(url)
+        .is_some_and(|cap| MAPS_HOST_REGEX.is_match(&cap[1]))
+}
+
+/// Returns the target of a Google redirect link (`/url?q=<target>&...`).
+pub fn unwrap_redirect(url: &str) -> Option<String> {
+    REDIRECT_REGEX.captures(url)
+        .and_then(|cap| decode(&cap[1]).ok().map(|s| s.into_owned()))
+}
+
+/// Builds a canonical share URL for a place, including its title and
+/// location when known.
+pub fn share_url(place: &Place) -> Result<String, UrlError> {
+    let feature_id = FeatureId::from_location_id(&place.location_id)?;
+    let title = place.title.as_deref()
+        .filter(|title| !title.is_empty())
+        .map(|title| encode(title).replace("%20", "+"));
+
+    let mut url = String::from("https://www.google.com/maps/place/");
+    if let Some(title) = &title {
+        url.push_str(title);
+        url.push('/');
+    }
+    match place.lat_lng() {
+        Some((lat, lng)) => url.push_str(&format!(
+            "@{lat},{lng},{DEFAULT_ZOOM}z/data=!4m6!3m5!1s{feature_id}!8m2!3d{lat}!4d{lng}"
+        )),
+        None => url.push_str(&format!("data=!4m2!3m1!1s{feature_id}")),
+    }
+    Ok(url)
+}
+
+/// Builds a `?cid=` URL for a place.
+pub fn cid_url(place: &Place) -> Result<String, UrlError> {
+    let feature_id = FeatureId::from_location_id(&place.location_id)?;
+    Ok(format!("https://maps.google.com/?cid={}", feature_id.cid()))
+}
+
+fn decode_component(component: &str) -> String {
+    let component = component.replace('+', " ");
+    decode(&component)
+        .map(|s| s.into_owned())
+        .unwrap_or(component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPIRE_STATE_BUILDING: &str = "https://www.google.com/maps/place/data=!3m2!4b1!5s0x0:0x0!4m6!3m5!1s0x0:0xd134e199a405a163!8m2!3d40.7484405!4d-73.9856644!16zL20vMDJuZF8";
+
+    #[test]
+    fn test_parse_place() {
+        let url = parse(EMPIRE_STATE_BUILDING).unwrap();
+        assert_eq!(url.kind, UrlKind::Place);
+        assert_eq!(url.feature_id.unwrap().location_id(), "0d134e199a405a163");
+        assert_eq!(url.latitude, Some(40.7484405));
+        assert_eq!(url.longitude, Some(-73.9856644));
+        assert_eq!(url.title, None);
+
+        let url = parse("https://www.google.com/maps/place/Empire+State+Building/@40.7484405,-73.9878531,17z/data=!3m1!4b1!4m6!3m5!1s0x89c259a9b3117469:0xd134e199a405a163!8m2!3d40.7484405!4d-73.9856644").unwrap();
+        assert_eq!(url.title.as_deref(), Some("Empire State Building"));
+        assert_eq!(url.longitude, Some(-73.9856644));
+        assert_eq!(url.zoom, Some(17.0));
+        assert_eq!(url.feature_id.unwrap().to_string(), "0x89c259a9b3117469:0xd134e199a405a163");
+    }
+
+    #[test]
+    fn test_location_id_round_trip() {
+        let ids = [
+            FeatureId { cell_id: 0, id: 0xd134e199a405a163 },
+            FeatureId { cell_id: 0x89c259a9b3117469, id: 0xd134e199a405a163 },
+            FeatureId { cell_id: 0x1, id: 0x23 },
+            FeatureId { cell_id: 0x12, id: 0x3 },
+        ];
+        for id in ids {
+            assert_eq!(FeatureId::from_location_id(&id.location_id()), Ok(id));
+        }
+        assert_ne!(ids[2].location_id(), ids[3].location_id());
+
+        assert_eq!(FeatureId::from_location_id("d134e199a405a163").unwrap().id, 0xd134e199a405a163);
+        assert!(FeatureId::from_location_id("").is_err());
+        assert!(FeatureId::from_location_id("+d134e199a405a163").is_err());
+    }
+
+    #[test]
+    fn test_parse_search_and_cid() {
+        let url = parse("https://www.google.com/maps/search/pizza+near+caf%C3%A9/@40.74,-73.98,15.5z").unwrap();
+        assert_eq!(url.kind, UrlKind::Search);
+        assert_eq!(url.title.as_deref(), Some("pizza near café"));
+        assert_eq!(url.zoom, Some(15.5));
+
+        let url = parse("https://maps.google.com/?cid=15074921902713971043").unwrap();
+        assert_eq!(url.kind, UrlKind::Cid);
+        assert_eq!(url.feature_id.unwrap().id, 0xd134e199a405a163);
+        assert_eq!(parse("google.co.uk/maps?cid=15074921902713971043").unwrap().kind, UrlKind::Cid);
+
+        assert!(parse("https://example.com/").is_err());
+        assert!(parse("https://example.com/?cid=123").is_err());
+        assert!(parse("https://google.com.evil.example/?cid=123").is_err());
+    }
+
+    #[test]
+    fn test_unwrap_redirect() {
+        let redirect = "/url?q=https://www.esbnyc.com/%3Futm_source%3Dgmb&opi=79508299&sa=U";
+        assert_eq!(unwrap_redirect(redirect).as_deref(), Some("https://www.esbnyc.com/?utm_source=gmb"));
+
+        let url = format!("https://www.google.com/url?q={}", encode(EMPIRE_STATE_BUILDING));
+        assert_eq!(parse(&url).unwrap(), parse(EMPIRE_STATE_BUILDING).unwrap());
+    }
+
+    #[test]
+    fn test_share_url() {
+        let place = Place {
+            location_id: "0d134e199a405a163".to_string(),
+            title: Some("Empire State Building".to_string()),
+            local_language_title: None,
+            rating: None,
+            phone: None,
+            url: None,
+            menu_url: None,
+            global_code: None,
+            compound_code: None,
+            altitude: None,
+            longitude: Some(-739856644),
+            latitude: Some(407484405),
+        };
+
+        let url = parse(&share_url(&place).unwrap()).unwrap();
+        assert_eq!(url.feature_id.unwrap().location_id(), place.location_id);
+        assert_eq!(url.title, place.title);
+        assert_eq!(url.latitude, Some(40.7484405));
+
+        let url = parse(&cid_url(&place).unwrap()).unwrap();
+        assert_eq!(url.feature_id.unwrap().location_id(), place.location_id);
+    }
+}
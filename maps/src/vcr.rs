@@ -0,0 +1,206 @@
+//! Record/replay of tile and `GetEntityDetails` exchanges, for regression
+//! tests that run against real responses without network.
+//!
+//! A cassette is a directory with one JSON file per request, under `tiles/`
+//! and `places/`. Files are named after a hash of the request, so replay
+//! serves whatever was recorded for an identical request and fails with a
+//! "not recorded" error otherwise.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::Bytes;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tonic::{Code, Request, Response, Status};
+use crate::builder::ServiceClient;
+use crate::mapsjs::GetEntityDetailsRequest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VcrMode {
+    /// Send requests and save the responses to the cassette.
+    Record,
+    /// Answer requests from the cassette without sending them.
+    Replay,
+}
+
+/// A cassette directory and what to do with it. Clones share the cassette.
+#[derive(Debug, Clone)]
+pub struct Vcr {
+    dir: Arc<PathBuf>,
+    mode: VcrMode,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TileEntry {
+    request: String,
+    status: u16,
+    body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlaceEntry {
+    feature_id: String,
+    language: String,
+    region: String,
+    code: i32,
+    message: String,
+    response: String,
+}
+
+impl Vcr {
+    /// Records into `dir`, creating it if needed. Existing recordings of the
+    /// same requests are overwritten.
+    pub fn record(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(dir.join("tiles"))?;
+        std::fs::create_dir_all(dir.join("places"))?;
+        Ok(Self { dir: Arc::new(dir), mode: VcrMode::Record })
+    }
+
+    /// Replays from `dir`.
+    pub fn replay(dir: impl AsRef<Path>) -> Self {
+        Self { dir: Arc::new(dir.as_ref().to_path_buf()), mode: VcrMode::Replay }
+    }
+
+    pub fn mode(&self) -> VcrMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn tile_path(&self, request: &str) -> PathBuf {
+        self.dir.join("tiles").join(format!("{:016x}.json", fnv1a(request.as_bytes())))
+    }
+
+    fn place_path(&self, request: &GetEntityDetailsRequest) -> PathBuf {
+        self.dir.join("places").join(format!("{:016x}.json", fnv1a(&request.encode_to_vec())))
+    }
+
+    /// Saves a tile response. `request` is the path and query, without the host.
+    pub(crate) async fn record_tile(&self, request: &str, status: u16, body: &[u8]) -> io::Result<()> {
+        let entry = TileEntry {
+            request: request.to_string(),
+            status,
+            body: STANDARD.encode(body),
+        };
+        write_entry(&self.tile_path(request), &entry).await
+    }
+
+    /// Returns the recorded status and body, or `None` if `request` wasn't recorded.
+    pub(crate) async fn replay_tile(&self, request: &str) -> io::Result<Option<(u16, Bytes)>> {
+        let Some(entry) = read_entry::<TileEntry>(&self.tile_path(request)).await? else {
+            return Ok(None);
+        };
+        let body = STANDARD.decode(entry.body)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some((entry.status, body.into())))
+    }
+
+    async fn record_place(&self, request: &GetEntityDetailsRequest, result: &Result<Bytes, Status>) -> io::Result<()> {
+        let (feature_id, language, region) = describe(request);
+        let entry = match result {
+            Ok(bytes) => PlaceEntry {
+                feature_id,
+                language,
+                region,
+                code: Code::Ok as i32,
+                message: String::new(),
+                response: STANDARD.encode(bytes),
+            },
+            Err(status) => PlaceEntry {
+                feature_id,
+                language,
+                region,
+                code: status.code() as i32,
+                message: status.message().to_string(),
+                response: String::new(),
+            },
+        };
+        write_entry(&self.place_path(request), &entry).await
+    }
+
+    async fn replay_place(&self, request: &GetEntityDetailsRequest) -> io::Result<Option<Result<Bytes, Status>>> {
+        let Some(entry) = read_entry::<PlaceEntry>(&self.place_path(request)).await? else {
+            return Ok(None);
+        };
+        if entry.code != Code::Ok as i32 {
+            return Ok(Some(Err(Status::new(Code::from(entry.code), entry.message))));
+        }
+        let bytes = STANDARD.decode(entry.response)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Some(Ok(bytes.into())))
+    }
+}
+
+/// Wraps another service client to record its exchanges, or replaces it
+/// entirely when replaying.
+#[derive(Clone)]
+pub(crate) struct VcrClient {
+    pub(crate) inner: ServiceClient,
+    pub(crate) vcr: Vcr,
+}
+
+impl VcrClient {
+    pub(crate) async fn get_entity_details(
+        &mut self,
+        request: Request<GetEntityDetailsRequest>,
+    ) -> Result<Response<Bytes>, Status> {
+        let message = request.get_ref().clone();
+
+        if self.vcr.mode == VcrMode::Replay {
+            let (feature_id, language, region) = describe(&message);
+            return match self.vcr.replay_place(&message).await {
+                Ok(Some(result)) => result.map(Response::new),
+                Ok(None) => Err(Status::failed_precondition(format!(
+                    "No recorded response for {} ({}, {}) in {}",
+                    feature_id, language, region, self.vcr.dir.display()
+                ))),
+                Err(e) => Err(Status::internal(format!("Failed to read cassette: {}", e))),
+            };
+        }
+
+        let result = Box::pin(self.inner.get_entity_details(request)).await;
+        let recorded = match &result {
+            Ok(response) => Ok(response.get_ref().clone()),
+            Err(status) => Err(status.clone()),
+        };
+        self.vcr.record_place(&message, &recorded).await
+            .map_err(|e| Status::internal(format!("Failed to write cassette: {}", e)))?;
+        result
+    }
+}
+
+fn describe(request: &GetEntityDetailsRequest) -> (String, String, String) {
+    let feature_id = request.entity_query.as_ref()
+        .and_then(|query| query.entity.as_ref())
+        .map(|entity| entity.feature_id.clone())
+        .unwrap_or_default();
+    let (language, region) = request.localization_context.as_ref()
+        .map(|context| (context.language.clone(), context.region.clone()))
+        .unwrap_or_default();
+    (feature_id, language, region)
+}
+
+async fn write_entry<T: Serialize>(path: &Path, entry: &T) -> io::Result<()> {
+    let json = serde_json::to_vec_pretty(entry)?;
+    tokio::fs::write(path, json).await
+}
+
+async fn read_entry<T: for<'de> Deserialize<'de>>(path: &Path) -> io::Result<Option<T>> {
+    match tokio::fs::read(path).await {
+        Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter()
+        .fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
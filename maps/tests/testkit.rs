@@ -0,0 +1,345 @@
+use std::time::Duration;
+use bytes::Bytes;
+use futures::StreamExt;
+use http_body_util::Empty;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use maps::tiles::{FetchTilesError, Tile, TileCache, TilesClient, DEFAULT_MAP_VERSION};
+use maps::{GetPlaceError, GrpcWebMode, MapsJsInternalClient, RequestError, Transport};
+use maps_testkit::{synthetic_features, Fault, MapsTestServer};
+
+fn tiles_client(server: &MapsTestServer) -> TilesClient {
+    let client = Client::builder(TokioExecutor::new())
+        .build::<_, Empty<Bytes>>(HttpsConnector::new());
+    TilesClient::new(client).base_url(server.tiles_url())
+}
+
+async fn places_client(server: &MapsTestServer) -> MapsJsInternalClient {
+    MapsJsInternalClient::builder()
+        .url(server.places_url())
+        .build()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_view_tiles() {
+    let server = MapsTestServer::start().await.unwrap();
+    let tiles: Vec<Tile> = (0..4).map(|x| Tile { x, y: 7, zoom: 16 }).collect();
+
+    let expected: Vec<String> = tiles.iter()
+        .flat_map(|tile| synthetic_features(tile.zoom, tile.x, tile.y))
+        .map(|feature| feature.hex_id())
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(tiles_client(&server).view_tiles(&tiles).await.unwrap(), expected);
+
+    let features = tiles_client(&server).language("de-DE").view_tile_features(&tiles).await.unwrap();
+    assert!(features.iter().all(|feature| feature.label.as_deref().is_some_and(|label| label.ends_with("[de-DE]"))));
+
+    let expected: Vec<(f64, f64)> = tiles.iter()
+        .flat_map(|tile| synthetic_features(tile.zoom, tile.x, tile.y).into_iter()
+            .map(|feature| tile.to_lat_lng(feature.anchor.0 as f64 / 256.0, feature.anchor.1 as f64 / 256.0)))
+        .collect();
+    let (features, _) = tiles_client(&server).view_tile_positions(&tiles).await.unwrap();
+    assert_eq!(features.iter().filter_map(|feature| feature.lat_lng).collect::<Vec<_>>(), expected);
+}
+
+#[tokio::test]
+async fn test_view_tiles_faults() {
+    let server = MapsTestServer::start().await.unwrap();
+    let client = tiles_client(&server);
+    let tiles = vec![Tile { x: 1, y: 2, zoom: 16 }];
+
+    server.tile_faults().push(Fault::RateLimit);
+    server.tile_faults().push(Fault::ServerError(503));
+    server.tile_faults().push(Fault::TruncatedJson);
+    server.tile_faults().push(Fault::Captcha);
+    server.tile_faults().push(Fault::Latency(Duration::from_millis(50)));
+
+    assert!(matches!(client.view_tiles(&tiles).await, Err(FetchTilesError::UnexpectedStatusCode(429))));
+    assert!(matches!(client.view_tiles(&tiles).await, Err(FetchTilesError::UnexpectedStatusCode(503))));
+    assert!(matches!(client.view_tiles(&tiles).await, Err(FetchTilesError::ParseError(_))));
+    assert!(matches!(client.view_tiles(&tiles).await, Err(FetchTilesError::ParseError(_))));
+    assert!(client.view_tiles(&tiles).await.is_ok());
+    assert_eq!(server.tile_requests(), 5);
+}
+
+#[tokio::test]
+async fn test_view_tiles_encodings() {
+    let server = MapsTestServer::start().await.unwrap();
+    let tiles: Vec<Tile> = (0..160).map(|x| Tile { x, y: 7, zoom: 16 }).collect();
+
+    let client = tiles_client(&server).compression(false);
+    let expected = client.view_tiles(&tiles).await.unwrap();
+    let plain = client.byte_counts();
+    assert_eq!(plain.received, plain.decoded);
+
+    // The fake server prefers brotli, like the real one
+    let client = tiles_client(&server);
+    assert_eq!(client.view_tiles(&tiles).await.unwrap(), expected);
+    let compressed = client.byte_counts();
+    assert_eq!(compressed.decoded, plain.decoded);
+    assert!(compressed.received < compressed.decoded / 2);
+
+    server.tile_faults().push(Fault::XssiPrefix);
+    assert_eq!(client.view_tiles(&tiles).await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_refresh_map_version() {
+    let server = MapsTestServer::start().await.unwrap();
+    let client = tiles_client(&server);
+    let clone = client.clone();
+    let tiles = vec![Tile { x: 1, y: 2, zoom: 16 }];
+    assert_eq!(client.discover_map_version().await.unwrap(), DEFAULT_MAP_VERSION);
+
+    // The rejected request finds the new version, and clones share it
+    server.set_map_version("702460000");
+    let Err(FetchTilesError::StaleMapVersion(stale)) = client.view_tiles(&tiles).await else {
+        panic!("expected a stale map version");
+    };
+    assert_eq!(stale, DEFAULT_MAP_VERSION);
+    assert_eq!(clone.current_map_version(), "702460000");
+
+    // A second refresh of the same stale version is a no-op
+    let requests = server.tile_requests();
+    assert_eq!(clone.refresh_map_version(&stale).await.unwrap(), "702460000");
+    assert_eq!(server.tile_requests(), requests);
+
+    assert!(clone.view_tiles(&tiles).await.is_ok());
+
+    // A discovery that finds nothing newer is shared for the cooldown
+    let requests = server.tile_requests();
+    assert_eq!(client.refresh_map_version("702460000").await.unwrap(), "702460000");
+    assert_eq!(clone.refresh_map_version("702460000").await.unwrap(), "702460000");
+    assert_eq!(server.tile_requests(), requests + 1);
+}
+
+#[tokio::test]
+async fn test_view_tiles_bad_request() {
+    let server = MapsTestServer::start().await.unwrap();
+    // Every tile request is a 404, but the version is still current
+    let client = tiles_client(&server)
+        .base_url(format!("{}/wrong", server.tiles_url()))
+        .bootstrap_url(format!("{}/maps/api/js", server.tiles_url()));
+    let tiles = vec![Tile { x: 1, y: 2, zoom: 16 }];
+
+    assert!(matches!(client.view_tiles(&tiles).await, Err(FetchTilesError::UnexpectedStatusCode(404))));
+    assert_eq!(server.tile_requests(), 2);
+    // Not discovered again during the cooldown
+    assert!(matches!(client.view_tiles(&tiles).await, Err(FetchTilesError::UnexpectedStatusCode(404))));
+    assert_eq!(server.tile_requests(), 3);
+    assert_eq!(client.current_map_version(), DEFAULT_MAP_VERSION);
+}
+
+#[tokio::test]
+async fn test_refresh_map_version_failed() {
+    let server = MapsTestServer::start().await.unwrap();
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let client = tiles_client(&server)
+        .bootstrap_url(format!("http://{}/maps/api/js", addr))
+        .map_version_refresh_cooldown(Duration::from_millis(200));
+
+    assert!(matches!(client.refresh_map_version(DEFAULT_MAP_VERSION).await, Err(FetchTilesError::HyperClientError(_))));
+    assert!(matches!(
+        client.clone().refresh_map_version(DEFAULT_MAP_VERSION).await,
+        Err(FetchTilesError::MapVersionRefreshFailed(_))
+    ));
+
+    // Discovered again once the cooldown is over
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert!(matches!(client.refresh_map_version(DEFAULT_MAP_VERSION).await, Err(FetchTilesError::HyperClientError(_))));
+}
+
+#[tokio::test]
+async fn test_get_place() {
+    let server = MapsTestServer::start().await.unwrap();
+    let client = places_client(&server).await;
+
+    let place = client.get_place("0d134e199a405a163".to_string())
+        .language("de-DE")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(place.title.as_deref(), Some("Place 0d134e199a405a163"));
+    assert_eq!(place.local_language_title.as_deref(), Some("Place 0d134e199a405a163 (de-DE)"));
+    assert_eq!(place.url.as_deref(), Some("https://example.com/0d134e199a405a163"));
+    assert!(place.lat_lng().is_some());
+
+    server.places().not_found("missing");
+    let result = client.get_place("missing".to_string()).send().await;
+    assert!(matches!(result, Err(GetPlaceError::RequestError(RequestError::NotFound))));
+}
+
+#[tokio::test]
+async fn test_get_places() {
+    let server = MapsTestServer::start().await.unwrap();
+    let client = places_client(&server).await;
+    server.places().not_found("2");
+    let ids: Vec<String> = (0..6).map(|i| i.to_string()).collect();
+
+    // The first lookup is the slowest, but results still come back in input order
+    server.place_faults().push(Fault::Latency(Duration::from_millis(300)));
+    let results: Vec<_> = client.get_places(futures::stream::iter(ids.clone()), 4).collect().await;
+    let order: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(order, ["0", "1", "2", "3", "4", "5"]);
+    for (id, result) in &results {
+        match id.as_str() {
+            "2" => assert!(matches!(result, Err(GetPlaceError::RequestError(RequestError::NotFound)))),
+            _ => assert_eq!(result.as_ref().unwrap().title, Some(format!("Place {}", id))),
+        }
+    }
+
+    server.place_faults().push(Fault::Latency(Duration::from_millis(300)));
+    let results: Vec<_> = client.get_places_unordered(futures::stream::iter(ids.clone()), 4).collect().await;
+    assert_eq!(results.len(), 6);
+    assert_eq!(results.last().unwrap().0, "0");
+    assert_eq!(results.iter().filter(|(_, result)| result.is_err()).count(), 1);
+
+    // A concurrency of 0 is treated as 1 instead of never polling
+    let results: Vec<_> = client.get_places(futures::stream::iter(ids.clone()), 0).collect().await;
+    assert_eq!(results.len(), 6);
+    let results: Vec<_> = client.get_places_unordered(futures::stream::iter(ids), 0).collect().await;
+    assert_eq!(results.len(), 6);
+}
+
+#[tokio::test]
+async fn test_get_place_pooled() {
+    let server = MapsTestServer::start().await.unwrap();
+    let client = MapsJsInternalClient::builder()
+        .url(server.places_url())
+        .pool_size(4)
+        .build()
+        .await
+        .unwrap();
+
+    let lookups = (0..32).map(|i| {
+        let client = client.clone();
+        tokio::spawn(async move {
+            let id = format!("{:x}", i);
+            let place = client.get_place(id.clone()).send().await.unwrap();
+            assert_eq!(place.title, Some(format!("Place {}", id)));
+        })
+    });
+    for lookup in lookups.collect::<Vec<_>>() {
+        lookup.await.unwrap();
+    }
+    assert_eq!(server.place_requests(), 32);
+
+    // Unreachable endpoints fail to build, as with a single connection
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let result = MapsJsInternalClient::builder()
+        .url(format!("http://{}", addr))
+        .pool_size(4)
+        .build()
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_get_place_grpc_web() {
+    let server = MapsTestServer::start().await.unwrap();
+    server.places().not_found("missing");
+
+    for mode in [GrpcWebMode::Binary, GrpcWebMode::Text] {
+        let client = MapsJsInternalClient::builder()
+            .url(server.grpc_web_url())
+            .transport(Transport::GrpcWeb(mode))
+            .build()
+            .await
+            .unwrap();
+
+        let place = client.get_place("0d134e199a405a163".to_string()).send().await.unwrap();
+        assert_eq!(place.title.as_deref(), Some("Place 0d134e199a405a163"));
+
+        let result = client.get_place("missing".to_string()).send().await;
+        assert!(matches!(result, Err(GetPlaceError::RequestError(RequestError::NotFound))));
+
+        server.place_faults().push(Fault::RateLimit);
+        let result = client.get_place("0d134e199a405a163".to_string()).send().await;
+        assert!(matches!(result, Err(GetPlaceError::RequestError(RequestError::RateLimited))));
+    }
+    assert_eq!(server.place_requests(), 6);
+
+    let result = MapsJsInternalClient::builder()
+        .url(server.grpc_web_url())
+        .transport(Transport::GrpcWeb(GrpcWebMode::Binary))
+        .keep_alive(Duration::from_secs(10), Duration::from_secs(5))
+        .build()
+        .await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_blocking_client() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(MapsTestServer::start()).unwrap();
+
+    let client = maps::blocking::Client::from_builder(MapsJsInternalClient::builder().url(server.places_url())).unwrap();
+    let place = client.clone().get_place("0d134e199a405a163").unwrap();
+    assert_eq!(place.title.as_deref(), Some("Place 0d134e199a405a163"));
+
+    // Dropping the last clone inside an async context must not panic
+    runtime.block_on(async move { drop(client) });
+}
+
+#[tokio::test]
+async fn test_get_place_faults() {
+    let server = MapsTestServer::start().await.unwrap();
+    let client = places_client(&server).await;
+
+    server.place_faults().push(Fault::RateLimit);
+    let result = client.get_place("0d134e199a405a163".to_string()).send().await;
+    assert!(matches!(result, Err(GetPlaceError::RequestError(RequestError::RateLimited))));
+
+    server.place_faults().push(Fault::Latency(Duration::from_secs(2)));
+    let result = client.get_place("0d134e199a405a163".to_string())
+        .timeout(Duration::from_millis(100))
+        .send()
+        .await;
+    assert!(matches!(result, Err(GetPlaceError::RequestError(RequestError::TonicStatus(_)))));
+
+    assert!(client.get_place("0d134e199a405a163".to_string()).send().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_view_tiles_cache() {
+    let server = MapsTestServer::start().await.unwrap();
+    let path = std::env::temp_dir().join(format!("maps-testkit-cache-{}.redb", std::process::id()));
+    let cache = TileCache::open(&path, Duration::from_secs(3600)).unwrap();
+    let client = tiles_client(&server).cache(cache.clone());
+
+    let first: Vec<Tile> = (0..4).map(|x| Tile { x, y: 7, zoom: 16 }).collect();
+    let overlapping: Vec<Tile> = (2..6).map(|x| Tile { x, y: 7, zoom: 16 }).collect();
+    let expected = tiles_client(&server).view_tiles(&overlapping).await.unwrap();
+
+    client.view_tiles(&first).await.unwrap();
+    assert_eq!(client.view_tiles(&overlapping).await.unwrap(), expected);
+    assert_eq!(client.view_tiles(&first).await.unwrap().len(), tiles_client(&server).view_tiles(&first).await.unwrap().len());
+    assert_eq!(cache.len().unwrap(), 6);
+    // Two uncached calls, plus one call per cached lookup that had misses
+    assert_eq!(server.tile_requests(), 4);
+
+    // Other languages are cached separately
+    let german = tiles_client(&server).cache(cache.clone()).language("de-DE");
+    german.view_tiles(&first).await.unwrap();
+    assert_eq!(cache.len().unwrap(), 10);
+
+    drop(server);
+    let offline = TilesClient::new(Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(HttpsConnector::new()))
+        .base_url("http://127.0.0.1:9")
+        .cache(cache.cache_only(true));
+    assert_eq!(offline.view_tiles(&overlapping).await.unwrap(), expected);
+
+    // Missing tiles are reported, and the cached ones in the batch still parsed
+    let partly_cached: Vec<Tile> = overlapping.iter().cloned().chain([Tile { x: 9, y: 9, zoom: 16 }]).collect();
+    let (ids, report) = offline.view_tiles_with_report(&partly_cached).await.unwrap();
+    assert_eq!(ids, expected);
+    assert_eq!(report.not_cached, 1);
+    assert!(report.is_clean());
+
+    drop(offline);
+    std::fs::remove_file(&path).unwrap();
+}
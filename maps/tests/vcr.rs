@@ -0,0 +1,66 @@
+use bytes::Bytes;
+use http_body_util::Empty;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use maps::tiles::{FetchTilesError, Tile, TilesClient};
+use maps::vcr::Vcr;
+use maps::{GetPlaceError, MapsJsInternalClient, RequestError};
+use maps_testkit::MapsTestServer;
+
+// Nothing listens here, so replays that reach the network fail.
+const UNREACHABLE: &str = "http://127.0.0.1:9";
+
+fn tiles_client(base_url: &str, vcr: Vcr) -> TilesClient {
+    let client = Client::builder(TokioExecutor::new())
+        .build::<_, Empty<Bytes>>(HttpsConnector::new());
+    TilesClient::new(client).base_url(base_url).vcr(vcr)
+}
+
+#[tokio::test]
+async fn test_record_and_replay() {
+    let cassette = std::env::temp_dir().join(format!("maps-vcr-{}", std::process::id()));
+    let tiles = vec![Tile { x: 3, y: 5, zoom: 16 }, Tile { x: 4, y: 5, zoom: 16 }];
+
+    let (recorded_ids, recorded_place) = {
+        let server = MapsTestServer::start().await.unwrap();
+        server.places().not_found("missing");
+        let vcr = Vcr::record(&cassette).unwrap();
+
+        let ids = tiles_client(&server.tiles_url(), vcr.clone()).view_tiles(&tiles).await.unwrap();
+        let client = MapsJsInternalClient::builder()
+            .url(server.places_url())
+            .vcr(vcr)
+            .build()
+            .await
+            .unwrap();
+        let place = client.get_place("0d134e199a405a163".to_string()).send().await.unwrap();
+        assert!(client.get_place("missing".to_string()).send().await.is_err());
+        (ids, place)
+    };
+
+    let vcr = Vcr::replay(&cassette);
+    let tiles_client = tiles_client(UNREACHABLE, vcr.clone());
+    assert_eq!(tiles_client.view_tiles(&tiles).await.unwrap(), recorded_ids);
+    assert!(matches!(
+        tiles_client.view_tiles(&vec![Tile { x: 9, y: 9, zoom: 16 }]).await,
+        Err(FetchTilesError::NotRecorded(_))
+    ));
+    assert!(matches!(tiles_client.discover_map_version().await, Err(FetchTilesError::NotRecorded(_))));
+
+    let client = MapsJsInternalClient::builder()
+        .url(UNREACHABLE)
+        .vcr(vcr)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(client.get_place("0d134e199a405a163".to_string()).send().await.unwrap(), recorded_place);
+    assert!(matches!(
+        client.get_place("missing".to_string()).send().await,
+        Err(GetPlaceError::RequestError(RequestError::NotFound))
+    ));
+    let result = client.get_place("unrecorded".to_string()).send().await;
+    assert!(result.is_err_and(|e| e.to_string().contains("No recorded response for unrecorded")));
+
+    std::fs::remove_dir_all(&cassette).unwrap();
+}